@@ -1,4 +1,4 @@
-#![doc(html_root_url = "https://docs.rs/gh-token/0.1.8")]
+#![doc(html_root_url = "https://docs.rs/gh-token/0.1.9")]
 #![allow(
     clippy::missing_errors_doc,
     clippy::module_name_repetitions,
@@ -7,6 +7,7 @@
 
 use crate::error::ParseError;
 use serde_derive::Deserialize;
+use std::collections::BTreeMap as Map;
 use std::env;
 use std::fmt::{self, Debug, Display};
 use std::fs;
@@ -15,9 +16,9 @@ use std::path::{Path, PathBuf};
 use std::process::Command;
 
 #[derive(Deserialize)]
+#[serde(transparent)]
 struct Config {
-    #[serde(rename = "github.com")]
-    github_com: Option<Host>,
+    hosts: Map<String, Host>,
 }
 
 #[derive(Deserialize)]
@@ -49,7 +50,7 @@ impl Display for Error {
             Error::NotConfigured(path) => {
                 write!(
                     formatter,
-                    "no github.com token found in {}; use `gh auth login` to authenticate",
+                    "no GitHub token found in {}; use `gh auth login` to authenticate",
                     path.display(),
                 )
             }
@@ -82,7 +83,24 @@ impl Debug for Error {
 }
 
 pub fn get() -> Result<String, Error> {
-    for var in ["GH_TOKEN", "GITHUB_TOKEN"] {
+    get_for_host("github.com")
+}
+
+/// Looks up the token for a specific GitHub host, such as a GitHub Enterprise
+/// Server instance.
+///
+/// For github.com, a token in `$GH_TOKEN` or `$GITHUB_TOKEN` takes precedence.
+/// For any other host those are ignored and `$GH_ENTERPRISE_TOKEN` or
+/// `$GITHUB_ENTERPRISE_TOKEN` is used instead, matching the `gh` CLI.
+/// Otherwise the token is read from the entry for `hostname` in gh's
+/// hosts.yml.
+pub fn get_for_host(hostname: &str) -> Result<String, Error> {
+    let vars = if is_github_com(hostname) {
+        ["GH_TOKEN", "GITHUB_TOKEN"]
+    } else {
+        ["GH_ENTERPRISE_TOKEN", "GITHUB_ENTERPRISE_TOKEN"]
+    };
+    for var in vars {
         if let Some(token_from_env) = env::var_os(var) {
             return token_from_env
                 .into_string()
@@ -111,9 +129,11 @@ pub fn get() -> Result<String, Error> {
         Err(yaml_error) => return Err(Error::Parse(ParseError::Yaml(path, yaml_error))),
     };
 
-    if let Some(github_com) = config.github_com {
-        if let Some(oauth_token) = github_com.oauth_token {
-            return Ok(oauth_token);
+    for (host_key, host) in config.hosts {
+        if host_key.eq_ignore_ascii_case(hostname) {
+            if let Some(oauth_token) = host.oauth_token {
+                return Ok(oauth_token);
+            }
         }
     }
 
@@ -123,7 +143,7 @@ pub fn get() -> Result<String, Error> {
     //
     // "As of right now storing the authentication token in the system keyring
     // is an opt-in feature, but in the near future it will be required"
-    if let Some(token) = token_from_cli() {
+    if let Some(token) = token_from_cli(hostname) {
         return Ok(token);
     }
 
@@ -133,6 +153,10 @@ pub fn get() -> Result<String, Error> {
     Err(Error::NotConfigured(path))
 }
 
+fn is_github_com(hostname: &str) -> bool {
+    hostname.eq_ignore_ascii_case("github.com")
+}
+
 fn hosts_config_file() -> Option<PathBuf> {
     let config_dir = config_dir()?;
     Some(config_dir.join("hosts.yml"))
@@ -163,8 +187,14 @@ fn config_dir() -> Option<PathBuf> {
     Some(home_dir.join(".config").join("gh"))
 }
 
-fn token_from_cli() -> Option<String> {
-    let output = Command::new("gh").arg("auth").arg("token").output().ok()?;
+fn token_from_cli(hostname: &str) -> Option<String> {
+    let output = Command::new("gh")
+        .arg("auth")
+        .arg("token")
+        .arg("--hostname")
+        .arg(hostname)
+        .output()
+        .ok()?;
     let mut token = String::from_utf8(output.stdout).ok()?;
     // Trim the captured trailing newline from CLI output
     let token_len = token.trim_end().len();
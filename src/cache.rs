@@ -0,0 +1,75 @@
+use crate::{Cursor, Result, Star};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeSet as Set;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+pub(crate) struct Cache {
+    dir: PathBuf,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct Entry {
+    pub cursor: Cursor,
+    pub stars: Vec<Star>,
+}
+
+#[derive(Serialize)]
+struct EntryRef<'a> {
+    cursor: &'a Cursor,
+    stars: &'a Set<Star>,
+}
+
+impl Cache {
+    pub fn new(host: &str) -> Option<Self> {
+        let base = match env::var_os("XDG_CACHE_HOME") {
+            Some(xdg_cache_home) if !xdg_cache_home.is_empty() => PathBuf::from(xdg_cache_home),
+            _ => home::home_dir()?.join(".cache"),
+        };
+        let mut dir = base.join("star-history");
+        if !host.eq_ignore_ascii_case(crate::DEFAULT_HOST) {
+            if !is_path_safe(host) {
+                return None;
+            }
+            dir = dir.join(host.to_lowercase());
+        }
+        Some(Cache { dir })
+    }
+
+    pub fn load(&self, owner: &str, repo: &str) -> Option<Entry> {
+        let path = self.path(owner, repo)?;
+        let content = fs::read(path).ok()?;
+        // A cache entry that fails to parse, e.g. from an older version of
+        // star-history, is treated the same as a missing one.
+        serde_json::from_slice(&content).ok()
+    }
+
+    pub fn store(&self, owner: &str, repo: &str, cursor: &Cursor, stars: &Set<Star>) -> Result<()> {
+        let Some(path) = self.path(owner, repo) else {
+            return Ok(());
+        };
+        fs::create_dir_all(path.parent().unwrap())?;
+        let entry = EntryRef { cursor, stars };
+        let json = serde_json::to_vec(&entry).unwrap();
+        fs::write(path, json)?;
+        Ok(())
+    }
+
+    fn path(&self, owner: &str, repo: &str) -> Option<PathBuf> {
+        if !is_path_safe(owner) || !is_path_safe(repo) {
+            return None;
+        }
+        let owner = owner.to_lowercase();
+        let repo = repo.to_lowercase();
+        Some(self.dir.join(owner).join(repo + ".json"))
+    }
+}
+
+fn is_path_safe(component: &str) -> bool {
+    !component.is_empty()
+        && !component.starts_with('.')
+        && component
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' || ch == '.')
+}
@@ -0,0 +1,421 @@
+use chrono::{
+    DateTime, Datelike, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone,
+    Timelike, Utc, Weekday,
+};
+
+pub(crate) struct Line {
+    pub name: String,
+    pub points: Vec<Point>,
+}
+
+pub(crate) struct Point {
+    pub time: DateTime<Utc>,
+    // Position along the x-axis in seconds: the Unix timestamp, or the time
+    // since the line's first star when plotting by age.
+    pub x: f64,
+    // Cumulative number of stars, or stars per period in velocity mode.
+    pub stars: f64,
+    // Empty for the points added at either end of a line to anchor it.
+    pub login: String,
+}
+
+#[derive(Copy, Clone, Default)]
+pub(crate) struct Axes {
+    pub log: bool,
+    pub age: bool,
+    pub per: Option<Period>,
+    pub unique: bool,
+}
+
+impl Axes {
+    pub fn star_label(self) -> String {
+        match (self.per, self.unique) {
+            (Some(period), false) => format!("Stars per {}", period.name()),
+            (Some(period), true) => format!("New stargazers per {}", period.name()),
+            (None, false) => "GitHub stars".to_owned(),
+            (None, true) => "Unique stargazers".to_owned(),
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub(crate) enum Period {
+    Day,
+    Week,
+    Month,
+}
+
+impl Period {
+    pub fn parse(string: &str) -> Option<Self> {
+        match string {
+            "day" => Some(Period::Day),
+            "week" => Some(Period::Week),
+            "month" => Some(Period::Month),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Period::Day => "day",
+            Period::Week => "week",
+            Period::Month => "month",
+        }
+    }
+
+    // Start of the period containing `time`, in UTC. Weeks start on Monday.
+    pub fn floor(self, time: DateTime<Utc>) -> DateTime<Utc> {
+        let date = time.date_naive();
+        let date = match self {
+            Period::Day => date,
+            Period::Week => {
+                date - TimeDelta::days(i64::from(date.weekday().num_days_from_monday()))
+            }
+            Period::Month => date.with_day(1).unwrap(),
+        };
+        date.and_time(NaiveTime::MIN).and_utc()
+    }
+
+    pub fn next(self, start: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Period::Day => start + TimeDelta::days(1),
+            Period::Week => start + TimeDelta::weeks(1),
+            Period::Month => start + Months::new(1),
+        }
+    }
+}
+
+// Same as d3.schemeCategory10, which index.html uses.
+pub(crate) const PALETTE: [&str; 10] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f",
+    "#bcbd22", "#17becf",
+];
+
+// Like d3.scaleOrdinal, lines with the same name get the same color.
+pub(crate) fn colors(lines: &[Line]) -> Vec<usize> {
+    let mut names = Vec::new();
+    lines
+        .iter()
+        .map(|line| {
+            let index = match names.iter().position(|name| *name == line.name) {
+                Some(index) => index,
+                None => {
+                    names.push(line.name.clone());
+                    names.len() - 1
+                }
+            };
+            index % PALETTE.len()
+        })
+        .collect()
+}
+
+// Mapping from data to chart coordinates, with the same domains as index.html:
+// the time axis starts a little before the first star and the star axis goes
+// a little above the highest count.
+pub(crate) struct Scale {
+    pub min_x: f64,
+    pub max_x: f64,
+    pub max_stars: f64,
+    pub axes: Axes,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Scale {
+    pub fn new(lines: &[Line], width: f64, height: f64, axes: Axes) -> Self {
+        let first = lines.iter().filter_map(|line| line.points.first());
+        let last = lines.iter().filter_map(|line| line.points.last());
+        let min_x = first.map(|point| point.x).reduce(f64::min).unwrap_or(0.0);
+        let max_x = last.clone().map(|point| point.x).reduce(f64::max);
+        let max_x = max_x.unwrap_or(0.0);
+        let points = lines.iter().flat_map(|line| &line.points);
+        let max_stars = points.map(|point| point.stars).fold(0.0, f64::max);
+        Scale {
+            min_x: (21.0 * min_x - max_x) / 20.0,
+            max_x,
+            max_stars: if axes.log {
+                max_stars.powf(1.025).max(10.0)
+            } else {
+                1.025 * max_stars
+            },
+            axes,
+            width,
+            height,
+        }
+    }
+
+    pub fn x(&self, x: f64) -> f64 {
+        let span = self.max_x - self.min_x;
+        if span == 0.0 {
+            return self.width / 2.0;
+        }
+        (x - self.min_x) / span * self.width
+    }
+
+    pub fn y(&self, stars: f64) -> f64 {
+        if self.axes.log {
+            // The log scale starts at 1 star, so the zero anchor at the start
+            // of each line sits on the axis together with the first star.
+            return self.height - stars.max(1.0).log10() / self.max_stars.log10() * self.height;
+        }
+        if self.max_stars == 0.0 {
+            return self.height / 2.0;
+        }
+        self.height - stars / self.max_stars * self.height
+    }
+
+    pub fn x_ticks(&self, count: usize) -> Vec<(f64, String)> {
+        if self.axes.age {
+            let ticks = linear_ticks(self.max_x / DAY, count);
+            return ticks
+                .into_iter()
+                .map(|(days, label)| (days * DAY, label))
+                .collect();
+        }
+        let min = DateTime::from_timestamp(self.min_x.ceil() as i64, 0).unwrap_or_default();
+        let max = DateTime::from_timestamp(self.max_x as i64, 0).unwrap_or_default();
+        let ticks = time_ticks(min, max, count);
+        ticks
+            .into_iter()
+            .map(|(time, label)| (time.timestamp() as f64, label))
+            .collect()
+    }
+
+    pub fn star_ticks(&self, count: usize) -> Vec<(f64, String)> {
+        if self.axes.log {
+            log_ticks(self.max_stars, count)
+        } else {
+            linear_ticks(self.max_stars, count)
+        }
+    }
+}
+
+// Equivalent of d3.ticks(0, max, count) along with d3's default tick format.
+fn linear_ticks(max: f64, count: usize) -> Vec<(f64, String)> {
+    if max <= 0.0 {
+        return vec![(0.0, "0".to_owned())];
+    }
+    let increment = tick_increment(max, count);
+    let power = increment.log10().floor();
+    let precision = if power < 0.0 { -power as usize } else { 0 };
+    let mut ticks = Vec::new();
+    let mut i = 0;
+    loop {
+        let value = i as f64 * increment;
+        if value > max {
+            break;
+        }
+        ticks.push((value, format_number(value, precision)));
+        i += 1;
+    }
+    ticks
+}
+
+// Equivalent of d3.scaleLog().domain([1, max]).ticks(count) and its tick
+// format, which leaves some of the ticks unlabeled when there are many.
+fn log_ticks(max: f64, count: usize) -> Vec<(f64, String)> {
+    let decades = max.log10();
+    let mut ticks = Vec::new();
+    if decades.ceil() < count as f64 {
+        for exponent in 0..=decades.ceil() as i32 {
+            let power = 10f64.powi(exponent);
+            for factor in 1..10 {
+                let value = f64::from(factor) * power;
+                if value <= max {
+                    ticks.push(value);
+                }
+            }
+        }
+    } else {
+        let step = tick_increment(decades, count).max(1.0);
+        let mut exponent = 0.0;
+        while exponent <= decades {
+            ticks.push(10f64.powf(exponent));
+            exponent += step;
+        }
+    }
+    let labeled = (10 * count / ticks.len().max(1)).max(1) as f64;
+    ticks
+        .into_iter()
+        .map(|value| {
+            let mut leading = value / 10f64.powf(value.log10().round());
+            if leading * 10.0 < 9.5 {
+                leading *= 10.0;
+            }
+            let label = if leading <= labeled {
+                format_number(value, 0)
+            } else {
+                String::new()
+            };
+            (value, label)
+        })
+        .collect()
+}
+
+// A step of 1, 2 or 5 times a power of 10 giving approximately `count` ticks
+// between 0 and `max`.
+fn tick_increment(max: f64, count: usize) -> f64 {
+    let step = max / count as f64;
+    let power = step.log10().floor();
+    let error = step / 10f64.powf(power);
+    let factor = if error >= 50f64.sqrt() {
+        10.0
+    } else if error >= 10f64.sqrt() {
+        5.0
+    } else if error >= 2f64.sqrt() {
+        2.0
+    } else {
+        1.0
+    };
+    factor * 10f64.powf(power)
+}
+
+// Formats with thousands separators, like d3.format(",").
+pub(crate) fn format_number(value: f64, precision: usize) -> String {
+    let formatted = format!("{:.*}", precision, value.abs());
+    let (integer, fraction) = match formatted.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (formatted.as_str(), None),
+    };
+    let mut string = String::new();
+    if value < 0.0 {
+        string.push('-');
+    }
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            string.push(',');
+        }
+        string.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        string.push('.');
+        string.push_str(fraction);
+    }
+    string
+}
+
+#[derive(Copy, Clone)]
+enum Interval {
+    Hour(u32),
+    Day(u32),
+    Week,
+    Month(u32),
+    Year(i32),
+}
+
+const HOUR: f64 = 3600.0;
+const DAY: f64 = 24.0 * HOUR;
+const YEAR: f64 = 365.0 * DAY;
+
+const INTERVALS: [(Interval, f64); 10] = [
+    (Interval::Hour(1), HOUR),
+    (Interval::Hour(3), 3.0 * HOUR),
+    (Interval::Hour(6), 6.0 * HOUR),
+    (Interval::Hour(12), 12.0 * HOUR),
+    (Interval::Day(1), DAY),
+    (Interval::Day(2), 2.0 * DAY),
+    (Interval::Week, 7.0 * DAY),
+    (Interval::Month(1), 30.0 * DAY),
+    (Interval::Month(3), 90.0 * DAY),
+    (Interval::Year(1), YEAR),
+];
+
+// Equivalent of d3.scaleTime().ticks(count) with d3's default multi-scale
+// tick format, in local time like in a browser.
+fn time_ticks(
+    min: DateTime<Utc>,
+    max: DateTime<Utc>,
+    count: usize,
+) -> Vec<(DateTime<Utc>, String)> {
+    if max <= min {
+        return Vec::new();
+    }
+    let target = (max - min).num_seconds() as f64 / count as f64;
+    let interval = match INTERVALS
+        .iter()
+        .position(|(_, duration)| *duration > target)
+    {
+        Some(0) => INTERVALS[0].0,
+        Some(i) => {
+            let (smaller, smaller_duration) = INTERVALS[i - 1];
+            let (larger, larger_duration) = INTERVALS[i];
+            if target / smaller_duration < larger_duration / target {
+                smaller
+            } else {
+                larger
+            }
+        }
+        None => {
+            let years = (max - min).num_seconds() as f64 / YEAR;
+            Interval::Year(tick_increment(years, count).max(1.0) as i32)
+        }
+    };
+
+    let min_local = min.with_timezone(&Local).naive_local();
+    let max_local = max.with_timezone(&Local).naive_local();
+    let mut candidate = floor(min_local, interval);
+    let mut ticks = Vec::new();
+    while candidate <= max_local {
+        if candidate >= min_local && is_aligned(candidate, interval) {
+            if let Some(time) = Local.from_local_datetime(&candidate).earliest() {
+                ticks.push((time.with_timezone(&Utc), tick_label(candidate)));
+            }
+        }
+        candidate = next(candidate, interval);
+    }
+    ticks
+}
+
+fn floor(time: NaiveDateTime, interval: Interval) -> NaiveDateTime {
+    let date = time.date();
+    match interval {
+        Interval::Hour(_) => date.and_hms_opt(time.hour(), 0, 0).unwrap(),
+        Interval::Day(_) | Interval::Week => date.and_hms_opt(0, 0, 0).unwrap(),
+        Interval::Month(_) => month_start(date.year(), date.month()),
+        Interval::Year(_) => month_start(date.year(), 1),
+    }
+}
+
+fn next(time: NaiveDateTime, interval: Interval) -> NaiveDateTime {
+    match interval {
+        Interval::Hour(_) => time + TimeDelta::hours(1),
+        Interval::Day(_) | Interval::Week => time + TimeDelta::days(1),
+        Interval::Month(_) => match time.month() {
+            12 => month_start(time.year() + 1, 1),
+            month => month_start(time.year(), month + 1),
+        },
+        Interval::Year(_) => month_start(time.year() + 1, 1),
+    }
+}
+
+fn is_aligned(time: NaiveDateTime, interval: Interval) -> bool {
+    match interval {
+        Interval::Hour(step) => time.hour().is_multiple_of(step),
+        Interval::Day(step) => time.day0().is_multiple_of(step),
+        Interval::Week => time.weekday() == Weekday::Sun,
+        Interval::Month(step) => time.month0().is_multiple_of(step),
+        Interval::Year(step) => time.year().rem_euclid(step) == 0,
+    }
+}
+
+fn month_start(year: i32, month: u32) -> NaiveDateTime {
+    let date = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+    date.and_hms_opt(0, 0, 0).unwrap()
+}
+
+fn tick_label(time: NaiveDateTime) -> String {
+    let format = if time.hour() != 0 {
+        "%I %p"
+    } else if time.day() != 1 {
+        if time.weekday() == Weekday::Sun {
+            "%b %d"
+        } else {
+            "%a %d"
+        }
+    } else if time.month() != 1 {
+        "%B"
+    } else {
+        "%Y"
+    };
+    time.format(format).to_string()
+}
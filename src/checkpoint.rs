@@ -0,0 +1,52 @@
+use crate::{Cursor, Error, Result, Series, Star, Work};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap as Map, BTreeSet as Set};
+use std::fs;
+use std::path::Path;
+
+#[derive(Deserialize)]
+pub(crate) struct Checkpoint {
+    pub args: Vec<Series>,
+    pub work: Vec<Work>,
+    pub stars: Map<Series, Set<Star>>,
+    pub cursors: Map<Series, Cursor>,
+}
+
+#[derive(Serialize)]
+struct CheckpointRef<'a> {
+    args: &'a [Series],
+    work: Vec<&'a Work>,
+    stars: &'a Map<Series, Set<Star>>,
+    cursors: &'a Map<Series, Cursor>,
+}
+
+pub(crate) fn load(path: &Path) -> Result<Checkpoint> {
+    let content = fs::read(path).map_err(|err| Error::ReadCheckpoint(path.to_owned(), err))?;
+    serde_json::from_slice(&content).map_err(|err| Error::ParseCheckpoint(path.to_owned(), err))
+}
+
+pub(crate) fn save<'a>(
+    path: &Path,
+    args: &[Series],
+    work: impl IntoIterator<Item = &'a Work>,
+    stars: &Map<Series, Set<Star>>,
+    cursors: &Map<Series, Cursor>,
+) -> Result<()> {
+    let checkpoint = CheckpointRef {
+        args,
+        work: work.into_iter().collect(),
+        stars,
+        cursors,
+    };
+    let json = serde_json::to_vec(&checkpoint).unwrap();
+
+    // Write to a temporary file first so that a crash mid-write does not
+    // destroy the previous checkpoint.
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let write = || {
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, path)
+    };
+    write().map_err(|err| Error::WriteCheckpoint(path.to_owned(), err))
+}
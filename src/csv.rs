@@ -0,0 +1,43 @@
+use crate::chart::{Axes, Line};
+use chrono::SecondsFormat;
+
+// One row per star, in chronological order within each series. The stars
+// column is the running total for the series including that star.
+//
+// With --per, one row per period instead, with the number of new stars in the
+// period starting at that timestamp.
+pub(crate) fn render(lines: &[Line], axes: Axes) -> String {
+    let mut csv = String::new();
+    csv += if axes.per.is_some() {
+        "series,timestamp,stars\n"
+    } else {
+        "series,timestamp,login,stars\n"
+    };
+    for line in lines {
+        for point in &line.points {
+            if axes.per.is_none() && point.login.is_empty() {
+                continue;
+            }
+            csv += &field(&line.name);
+            csv.push(',');
+            csv += &point.time.to_rfc3339_opts(SecondsFormat::Secs, true);
+            csv.push(',');
+            if axes.per.is_none() {
+                csv += &field(&point.login);
+                csv.push(',');
+            }
+            csv += &point.stars.to_string();
+            csv.push('\n');
+        }
+    }
+    csv
+}
+
+// Quoting per RFC 4180.
+fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
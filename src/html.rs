@@ -0,0 +1,44 @@
+use crate::chart::{Axes, Line};
+
+pub(crate) fn render(lines: &[Line], axes: Axes) -> String {
+    let mut data = String::new();
+    data += "var data = [\n";
+    for line in lines {
+        data += "      {\"name\":\"";
+        data += &line.name;
+        data += "\", \"values\":[\n";
+        for point in &line.points {
+            data += "        {\"time\":";
+            data += &point.time.timestamp().to_string();
+            data += ", \"x\":";
+            data += &point.x.to_string();
+            data += ", \"stars\":";
+            data += &point.stars.to_string();
+            data += "},\n";
+        }
+        data += "      ]},\n";
+    }
+    data += "    ];";
+
+    // Inline D3 so that the graph renders without network access, and keeps
+    // rendering after d3js.org is long gone.
+    let d3 = format!("<script>\n{}</script>", include_str!("d3.v7.min.js"));
+    include_str!("index.html")
+        .replace(
+            r#"<script src="https://d3js.org/d3.v7.min.js"></script>"#,
+            &d3,
+        )
+        .replace("var data = [];", &data)
+        .replace(
+            "var logScale = false;",
+            &format!("var logScale = {};", axes.log),
+        )
+        .replace(
+            "var ageMode = false;",
+            &format!("var ageMode = {};", axes.age),
+        )
+        .replace(
+            "var starLabel = \"GitHub stars\";",
+            &format!("var starLabel = \"{}\";", axes.star_label()),
+        )
+}
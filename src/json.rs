@@ -0,0 +1,61 @@
+use crate::{Error, Result, Series, Star};
+use chrono::{DateTime, Utc};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap as Map, BTreeSet as Set};
+use std::fs;
+use std::path::Path;
+
+#[derive(Deserialize)]
+pub(crate) struct Export {
+    pub fetched_at: DateTime<Utc>,
+    pub series: Vec<ExportSeries>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ExportSeries {
+    pub name: Series,
+    pub stars: Set<Star>,
+    #[serde(default)]
+    pub departures: Set<Star>,
+}
+
+#[derive(Serialize)]
+struct ExportRef<'a> {
+    fetched_at: DateTime<Utc>,
+    series: Vec<ExportSeriesRef<'a>>,
+}
+
+#[derive(Serialize)]
+struct ExportSeriesRef<'a> {
+    name: &'a Series,
+    stars: &'a Set<Star>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    departures: Option<&'a Set<Star>>,
+}
+
+pub(crate) fn render(
+    args: &[Series],
+    stars: &Map<Series, Set<Star>>,
+    departures: &Map<Series, Set<Star>>,
+    now: DateTime<Utc>,
+) -> String {
+    let export = ExportRef {
+        fetched_at: now,
+        series: args
+            .iter()
+            .map(|arg| ExportSeriesRef {
+                name: arg,
+                stars: &stars[arg],
+                departures: departures.get(arg),
+            })
+            .collect(),
+    };
+    let mut json = serde_json::to_string_pretty(&export).unwrap();
+    json.push('\n');
+    json
+}
+
+pub(crate) fn load(path: &Path) -> Result<Export> {
+    let content = fs::read(path).map_err(|err| Error::ReadInput(path.to_owned(), err))?;
+    serde_json::from_slice(&content).map_err(|err| Error::ParseInput(path.to_owned(), err))
+}
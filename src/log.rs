@@ -2,33 +2,38 @@ use crate::Error;
 use anyhow::anyhow;
 use std::fmt;
 use std::io::{self, Stderr, Write};
+use std::sync::{Mutex, PoisonError};
 
 pub(crate) struct Log {
-    page: usize,
+    page: Mutex<usize>,
     stderr: Stderr,
 }
 
 impl Log {
     pub fn new() -> Self {
         Log {
-            page: 0,
+            page: Mutex::new(0),
             stderr: io::stderr(),
         }
     }
 
-    pub fn tick(&mut self) {
-        let _ = write!(self.stderr, ".");
-        let _ = self.stderr.flush();
-        self.page += 1;
+    pub fn tick(&self) {
+        let mut page = self.page.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut stderr = self.stderr.lock();
+        let _ = write!(stderr, ".");
+        let _ = stderr.flush();
+        *page += 1;
     }
 
-    pub fn note(&mut self, msg: &str) {
-        let _ = write!(self.stderr, "[{}]", msg);
-        let _ = self.stderr.flush();
-        self.page += 1;
+    pub fn note(&self, msg: &str) {
+        let mut page = self.page.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut stderr = self.stderr.lock();
+        let _ = write!(stderr, "[{}]", msg);
+        let _ = stderr.flush();
+        *page += 1;
     }
 
-    pub fn error(&mut self, err: Error) {
+    pub fn error(&self, err: Error) {
         let prefix = match err {
             Error::GitHub(_) => "", // already starts with "Error"
             _ => "Error: ",
@@ -36,18 +41,21 @@ impl Log {
         writeln!(self, "{}{:?}", prefix, anyhow!(err));
     }
 
-    pub fn write_fmt(&mut self, args: fmt::Arguments) {
-        if self.page > 0 {
-            let _ = writeln!(self.stderr);
-            self.page = 0;
+    pub fn write_fmt(&self, args: fmt::Arguments) {
+        let mut page = self.page.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut stderr = self.stderr.lock();
+        if *page > 0 {
+            let _ = writeln!(stderr);
+            *page = 0;
         }
-        let _ = self.stderr.write_fmt(args);
+        let _ = stderr.write_fmt(args);
     }
 }
 
 impl Drop for Log {
     fn drop(&mut self) {
-        if self.page > 0 {
+        let page = self.page.get_mut().unwrap_or_else(PoisonError::into_inner);
+        if *page > 0 {
             let _ = writeln!(self.stderr);
         }
     }
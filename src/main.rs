@@ -6,6 +6,10 @@
 
 #![allow(
     clippy::cast_lossless,
+    clippy::cast_possible_truncation,
+    clippy::cast_possible_wrap,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss,
     clippy::default_trait_access,
     clippy::let_underscore_untyped,
     // Clippy bug: https://github.com/rust-lang/rust-clippy/issues/7422
@@ -17,29 +21,53 @@
     clippy::uninlined_format_args,
 )]
 
+mod cache;
+mod chart;
+mod checkpoint;
+mod csv;
+mod html;
+mod json;
 mod log;
-
+mod overlap;
+mod raster;
+mod replay;
+mod size;
+mod svg;
+mod terminal;
+
+use crate::cache::Cache;
+use crate::chart::{Axes, Line, Period, Point};
 use crate::log::Log;
-use chrono::{DateTime, TimeDelta, Utc};
+use crate::replay::Outcome;
+use crate::size::Size;
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeDelta, Utc};
 use reqwest::blocking::Client;
-use reqwest::header::{AUTHORIZATION, RETRY_AFTER, USER_AGENT};
+use reqwest::header::{HeaderMap, AUTHORIZATION, RETRY_AFTER, USER_AGENT};
 use reqwest::StatusCode;
 use serde::de::{self, Deserialize, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
 use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
 use std::cmp::{self, Ordering};
+use std::collections::hash_map::RandomState;
 use std::collections::{BTreeMap as Map, BTreeSet as Set, VecDeque};
 use std::env;
 use std::fmt::{self, Display};
 use std::fs;
-use std::io;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Write};
 use std::marker::PhantomData;
 use std::mem;
 use std::num::ParseIntError;
+use std::path::{Path, PathBuf};
 use std::process;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
+const DEFAULT_HOST: &str = "github.com";
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(30);
+
 static VERSION: &str = concat!("star-history ", env!("CARGO_PKG_VERSION"));
 
 static HELP: &str = concat!(
@@ -52,12 +80,43 @@ Produce a graph showing number of GitHub stars of a user or repo over time.
 
 USAGE:
     gh auth login
-    star-history [USER ...] [USER/REPO ...]
+    star-history [USER ...] [USER/REPO ...] [NAME=SERIES+SERIES ...]
+    star-history 'SERIES&SERIES' 'SERIES&!SERIES'
 
 EXAMPLES:
     star-history dtolnay
     star-history dtolnay/syn dtolnay/quote
     star-history serde-rs/serde
+    star-history rust=rust-lang+rust-lang-nursery
+    star-history 'dtolnay/syn&!dtolnay/quote'
+
+OPTIONS:
+    --format FORMAT     Output format: html (default), svg, png, csv or json
+    --input FILE        Render a graph from a previous --format json output
+    --terminal          Draw the graph in the terminal instead of a file
+    --log               Use a logarithmic scale for the number of stars
+    --age               Plot days since each series' first star, not dates
+    --since DATE        Only graph stars from DATE on (YYYY-MM-DD or RFC 3339)
+    --until DATE        Graph stars as of DATE instead of as of now
+    --per PERIOD        Graph new stars per day, week or month
+    --smooth N          Average --per over a rolling window of N periods
+    --unique            Count each stargazer of a user's repos or of a group
+                        only once
+    -o, --output PATH   Where to write the graph, or - for stdout; the format
+                        is inferred from the extension unless --format is given
+    --no-open           Do not open the graph after writing it
+    --width PIXELS      Width of svg or png graph [default: 950]
+    --height PIXELS     Height of svg or png graph [default: 500]
+    --dpi DPI           Resolution of png graph [default: 96]
+    --no-cache          Do not read or write the local stargazer cache
+    --refresh SERIES    Discard cached stargazers of a user or repo
+    --host HOST         GitHub Enterprise Server hostname [env: GH_HOST]
+    -j, --jobs N        Number of GraphQL requests to keep in flight [default: 1]
+    --max-attempts N    Attempts per request before giving up [default: 5]
+    --checkpoint FILE   Periodically save progress of the fetch to FILE
+    --resume FILE       Continue an interrupted fetch from a checkpoint
+    --record DIR        Save every GitHub response into DIR (implies --no-cache)
+    --replay DIR        Serve GitHub responses from a previous --record
 ",
 );
 
@@ -66,28 +125,88 @@ Error: GitHub auth token is not set up.
 
 (Expected config file: {{path}})
 
-Run `gh auth login` to store a GitHub login token. The `gh` CLI
+Run `{{login}}` to store a GitHub login token. The `gh` CLI
 can be installed from <https://cli.github.com>.
 
 If you prefer not to use the `gh` CLI, you can instead provide
-a token to star-history through the GITHUB_TOKEN environment
-variable. Head to <https://github.com/settings/tokens> and click
+a token to star-history through the {{var}} environment
+variable. Head to <https://{{host}}/settings/tokens> and click
 \"Generate new token (classic)\". The default public access
 permission is sufficient -- you can leave all the checkboxes
 empty. Save the generated token somewhere like ~/.githubtoken
-and use `export GITHUB_TOKEN=$(cat ~/.githubtoken)`.
+and use `export {{var}}=$(cat ~/.githubtoken)`.
 ";
 
 #[derive(Error, Debug)]
 enum Error {
     #[error("Error from GitHub api: {0}")]
     GitHub(String),
+    #[error("GitHub timed out")]
+    Timeout,
+    #[error("GitHub responded with {0}")]
+    HttpStatus(StatusCode),
+    #[error("failed to fetch complete star history of {}", .0.join(", "))]
+    Incomplete(Vec<String>),
     #[error("failed to decode response body")]
     DecodeResponse(#[source] serde_json::Error),
     #[error("no such user: {0}")]
     NoSuchUser(String),
     #[error("no such repository: {0}/{1}")]
     NoSuchRepo(String, String),
+    #[error("invalid user or repository name: {0}")]
+    InvalidSeries(String),
+    #[error("unknown output format: {0}")]
+    UnknownFormat(String),
+    #[error("missing value for {0}")]
+    MissingValue(&'static str),
+    #[error("unknown option: {0}")]
+    UnknownOption(String),
+    #[error("invalid width: {0}")]
+    InvalidWidth(String),
+    #[error("invalid height: {0}")]
+    InvalidHeight(String),
+    #[error("invalid dpi: {0}")]
+    InvalidDpi(String),
+    #[error("invalid date: {0}")]
+    InvalidDate(String),
+    #[error("--since must be earlier than --until")]
+    EmptyRange,
+    #[error("unknown period: {0}")]
+    UnknownPeriod(String),
+    #[error("invalid smoothing window: {0}")]
+    InvalidSmooth(String),
+    #[error("--smooth requires --per")]
+    SmoothWithoutPer,
+    #[error("image of {0}x{1} pixels is too large")]
+    ImageTooLarge(u32, u32),
+    #[error("failed to encode png")]
+    EncodePng(#[source] png::EncodingError),
+    #[error("invalid number of jobs: {0}")]
+    InvalidJobs(String),
+    #[error("invalid maximum number of attempts: {0}")]
+    InvalidMaxAttempts(String),
+    #[error("environment variable $GH_HOST contains non-utf8 value")]
+    HostNonUtf8,
+    #[error("no recorded response at {}", .0.display())]
+    Replay(PathBuf, #[source] io::Error),
+    #[error("failed to record response to {}", .0.display())]
+    Record(PathBuf, #[source] io::Error),
+    #[error("series arguments cannot be combined with --resume")]
+    ResumeWithArgs,
+    #[error("series arguments cannot be combined with --input")]
+    InputWithArgs,
+    #[error("failed to read {}", .0.display())]
+    ReadInput(PathBuf, #[source] io::Error),
+    #[error("failed to parse {}", .0.display())]
+    ParseInput(PathBuf, #[source] serde_json::Error),
+    #[error("failed to write {}", .0.display())]
+    WriteOutput(PathBuf, #[source] io::Error),
+    #[error("failed to read checkpoint {}", .0.display())]
+    ReadCheckpoint(PathBuf, #[source] io::Error),
+    #[error("failed to parse checkpoint {}", .0.display())]
+    ParseCheckpoint(PathBuf, #[source] serde_json::Error),
+    #[error("failed to write checkpoint {}", .0.display())]
+    WriteCheckpoint(PathBuf, #[source] io::Error),
     #[error("failed to parse Retry-After header from GitHub")]
     RetryAfterStr(#[source] reqwest::header::ToStrError),
     #[error("failed to parse Retry-After header from GitHub")]
@@ -106,6 +225,22 @@ type Result<T, E = Error> = std::result::Result<T, E>;
 enum Series {
     Owner(String),
     Repo(String, String),
+    // A named combination of repos and owners, drawn as a single line.
+    Group(String, Vec<Series>),
+    // People who starred every series in the first list and none in the
+    // second, e.g. `tokio-rs/tokio&!async-rs/async-std`.
+    Overlap(Vec<Series>, Vec<Series>),
+}
+
+impl Series {
+    // The repos and owners whose stargazers need to be fetched.
+    fn members(&self) -> Vec<&Series> {
+        match self {
+            Series::Owner(_) | Series::Repo(..) => vec![self],
+            Series::Group(_, members) => members.iter().collect(),
+            Series::Overlap(include, exclude) => include.iter().chain(exclude).collect(),
+        }
+    }
 }
 
 impl Display for Series {
@@ -117,11 +252,52 @@ impl Display for Series {
                 formatter.write_str("/")?;
                 formatter.write_str(repo)?;
             }
+            Series::Group(name, members) => {
+                formatter.write_str(name)?;
+                formatter.write_str("=")?;
+                for (i, member) in members.iter().enumerate() {
+                    if i > 0 {
+                        formatter.write_str("+")?;
+                    }
+                    Display::fmt(member, formatter)?;
+                }
+            }
+            Series::Overlap(include, exclude) => {
+                for (i, member) in include.iter().enumerate() {
+                    if i > 0 {
+                        formatter.write_str("&")?;
+                    }
+                    Display::fmt(member, formatter)?;
+                }
+                for member in exclude {
+                    formatter.write_str("&!")?;
+                    Display::fmt(member, formatter)?;
+                }
+            }
         }
         Ok(())
     }
 }
 
+impl Serialize for Series {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Series {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let string = String::deserialize(deserializer)?;
+        parse_series(&string).map_err(de::Error::custom)
+    }
+}
+
 impl Ord for Series {
     fn cmp(&self, other: &Self) -> Ordering {
         match (self, other) {
@@ -132,8 +308,19 @@ impl Ord for Series {
                 (lowner.to_lowercase(), lrepo.to_lowercase())
                     .cmp(&(rowner.to_lowercase(), rrepo.to_lowercase()))
             }
-            (Series::Owner(_), Series::Repo(..)) => Ordering::Less,
-            (Series::Repo(..), Series::Owner(_)) => Ordering::Greater,
+            (Series::Group(lname, lmembers), Series::Group(rname, rmembers)) => lname
+                .to_lowercase()
+                .cmp(&rname.to_lowercase())
+                .then_with(|| lmembers.cmp(rmembers)),
+            (Series::Overlap(linclude, lexclude), Series::Overlap(rinclude, rexclude)) => {
+                (linclude, lexclude).cmp(&(rinclude, rexclude))
+            }
+            (Series::Owner(_), _)
+            | (Series::Repo(..), Series::Group(..) | Series::Overlap(..))
+            | (Series::Group(..), Series::Overlap(..)) => Ordering::Less,
+            (Series::Overlap(..), _)
+            | (Series::Group(..), Series::Owner(_) | Series::Repo(..))
+            | (Series::Repo(..), Series::Owner(_)) => Ordering::Greater,
         }
     }
 }
@@ -150,32 +337,75 @@ impl PartialEq for Series {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(transparent)]
 struct Cursor(Option<String>);
 
-impl Display for Cursor {
-    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        match &self.0 {
-            Some(cursor) => {
-                formatter.write_str("\"")?;
-                formatter.write_str(cursor)?;
-                formatter.write_str("\"")?;
-            }
-            None => formatter.write_str("null")?,
+#[derive(Serialize, Deserialize)]
+struct Work {
+    series: Series,
+    cursor: Cursor,
+}
+
+#[derive(Copy, Clone)]
+enum Format {
+    Html,
+    Svg,
+    Png,
+    Csv,
+    Json,
+}
+
+impl Format {
+    fn parse(format: &str) -> Option<Self> {
+        match format {
+            "html" => Some(Format::Html),
+            "svg" => Some(Format::Svg),
+            "png" => Some(Format::Png),
+            "csv" => Some(Format::Csv),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Html => "html",
+            Format::Svg => "svg",
+            Format::Png => "png",
+            Format::Csv => "csv",
+            Format::Json => "json",
         }
-        Ok(())
     }
 }
 
-struct Work {
-    series: Series,
-    cursor: Cursor,
+struct Output {
+    terminal: bool,
+    path: Option<PathBuf>,
+    open: bool,
+    format: Format,
+    axes: Axes,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    smooth: usize,
+    width: u32,
+    height: u32,
+    dpi: u32,
+}
+
+struct Api {
+    client: Client,
+    endpoint: String,
+    authorization: String,
+    max_attempts: u32,
+    record: Option<PathBuf>,
+    replay: Option<PathBuf>,
 }
 
 #[derive(Serialize)]
 struct Request {
     query: String,
+    variables: Map<String, Value>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -192,10 +422,20 @@ struct Message {
     message: String,
 }
 
+impl Message {
+    fn is_timeout(&self) -> bool {
+        let message = self.message.to_lowercase();
+        message.contains("something went wrong")
+            || message.contains("timeout")
+            || message.contains("timed out")
+    }
+}
+
 #[derive(Debug)]
 enum Data {
     Owner(Option<Owner>),
     Repo(Option<Repo>),
+    RateLimit(RateLimit),
 }
 
 #[derive(Deserialize, Debug)]
@@ -215,10 +455,12 @@ struct Repositories {
 struct Repo {
     name: String,
     owner: Account,
+    #[serde(rename = "stargazerCount")]
+    stargazer_count: Option<usize>,
     stargazers: Option<Stargazers>,
 }
 
-#[derive(Deserialize, Ord, PartialOrd, Eq, PartialEq, Clone, Default, Debug)]
+#[derive(Serialize, Deserialize, Ord, PartialOrd, Eq, PartialEq, Clone, Default, Debug)]
 struct Account {
     login: String,
 }
@@ -231,13 +473,21 @@ struct Stargazers {
     edges: Vec<Star>,
 }
 
-#[derive(Deserialize, Ord, PartialOrd, Eq, PartialEq, Clone, Debug)]
+#[derive(Serialize, Deserialize, Ord, PartialOrd, Eq, PartialEq, Clone, Debug)]
 struct Star {
     #[serde(rename = "starredAt")]
     time: DateTime<Utc>,
     node: Account,
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct RateLimit {
+    cost: u64,
+    remaining: u64,
+    reset_at: DateTime<Utc>,
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 struct PageInfo {
@@ -270,6 +520,12 @@ where
                 } else if key.starts_with("repo") {
                     let repo = map.next_value::<Option<Repo>>()?;
                     data.push_back(Data::Repo(repo));
+                } else if key == "rateLimit" {
+                    // Null if rate limiting is disabled on a GitHub Enterprise
+                    // Server instance.
+                    if let Some(rate_limit) = map.next_value::<Option<RateLimit>>()? {
+                        data.push_back(Data::RateLimit(rate_limit));
+                    }
                 } else {
                     map.next_value::<IgnoredAny>()?;
                 }
@@ -322,43 +578,255 @@ where
 }
 
 fn main() {
-    let ref mut log = Log::new();
+    let ref log = Log::new();
     if let Err(err) = try_main(log) {
         log.error(err);
         process::exit(1);
     }
 }
 
-fn try_main(log: &mut Log) -> Result<()> {
+fn try_main(log: &Log) -> Result<()> {
     let mut args = Vec::new();
-    for arg in env::args().skip(1) {
+    let mut use_cache = true;
+    let mut refresh = Set::new();
+    let mut host = None;
+    let mut jobs = 1;
+    let mut max_attempts = 5;
+    let mut checkpoint_path = None;
+    let mut resume = false;
+    let mut record = None;
+    let mut replay = None;
+    let mut input = None;
+    let mut format = None;
+    let mut output = Output {
+        terminal: false,
+        path: None,
+        open: true,
+        format: Format::Html,
+        axes: Axes::default(),
+        since: None,
+        until: None,
+        smooth: 1,
+        width: 950,
+        height: 500,
+        dpi: 96,
+    };
+    let mut argv = env::args().skip(1);
+    while let Some(arg) = argv.next() {
         if arg == "--help" {
             print!("{}", HELP);
             process::exit(0);
         } else if arg == "--version" {
             println!("{}", VERSION);
             process::exit(0);
+        } else if arg == "--no-cache" {
+            use_cache = false;
+            continue;
+        } else if arg == "--refresh" {
+            let value = argv.next().ok_or(Error::MissingValue("--refresh"))?;
+            let series = parse_series(&value)?;
+            refresh.extend(series.members().into_iter().cloned());
+            continue;
+        } else if arg == "--jobs" || arg == "-j" {
+            let value = argv.next().ok_or(Error::MissingValue("--jobs"))?;
+            jobs = match value.parse() {
+                Ok(0) | Err(_) => return Err(Error::InvalidJobs(value)),
+                Ok(jobs) => jobs,
+            };
+            continue;
+        } else if arg == "--max-attempts" {
+            let value = argv.next().ok_or(Error::MissingValue("--max-attempts"))?;
+            max_attempts = match value.parse() {
+                Ok(0) | Err(_) => return Err(Error::InvalidMaxAttempts(value)),
+                Ok(max_attempts) => max_attempts,
+            };
+            continue;
+        } else if arg == "--checkpoint" {
+            let value = argv.next().ok_or(Error::MissingValue("--checkpoint"))?;
+            checkpoint_path = Some(PathBuf::from(value));
+            continue;
+        } else if arg == "--resume" {
+            let value = argv.next().ok_or(Error::MissingValue("--resume"))?;
+            checkpoint_path = Some(PathBuf::from(value));
+            resume = true;
+            continue;
+        } else if arg == "--record" {
+            let value = argv.next().ok_or(Error::MissingValue("--record"))?;
+            record = Some(PathBuf::from(value));
+            continue;
+        } else if arg == "--replay" {
+            let value = argv.next().ok_or(Error::MissingValue("--replay"))?;
+            replay = Some(PathBuf::from(value));
+            continue;
+        } else if arg == "--format" {
+            let value = argv.next().ok_or(Error::MissingValue("--format"))?;
+            format = Some(Format::parse(&value).ok_or(Error::UnknownFormat(value))?);
+            continue;
+        } else if arg == "--width" {
+            let value = argv.next().ok_or(Error::MissingValue("--width"))?;
+            output.width = match value.parse() {
+                Ok(0) | Err(_) => return Err(Error::InvalidWidth(value)),
+                Ok(width) => width,
+            };
+            continue;
+        } else if arg == "--height" {
+            let value = argv.next().ok_or(Error::MissingValue("--height"))?;
+            output.height = match value.parse() {
+                Ok(0) | Err(_) => return Err(Error::InvalidHeight(value)),
+                Ok(height) => height,
+            };
+            continue;
+        } else if arg == "--dpi" {
+            let value = argv.next().ok_or(Error::MissingValue("--dpi"))?;
+            output.dpi = match value.parse() {
+                Ok(0) | Err(_) => return Err(Error::InvalidDpi(value)),
+                Ok(dpi) => dpi,
+            };
+            continue;
+        } else if arg == "--output" || arg == "-o" {
+            let value = argv.next().ok_or(Error::MissingValue("--output"))?;
+            output.path = Some(PathBuf::from(value));
+            continue;
+        } else if arg == "--no-open" {
+            output.open = false;
+            continue;
+        } else if arg == "--log" {
+            output.axes.log = true;
+            continue;
+        } else if arg == "--age" {
+            output.axes.age = true;
+            continue;
+        } else if arg == "--per" {
+            let value = argv.next().ok_or(Error::MissingValue("--per"))?;
+            output.axes.per = Some(Period::parse(&value).ok_or(Error::UnknownPeriod(value))?);
+            continue;
+        } else if arg == "--smooth" {
+            let value = argv.next().ok_or(Error::MissingValue("--smooth"))?;
+            output.smooth = match value.parse() {
+                Ok(0) | Err(_) => return Err(Error::InvalidSmooth(value)),
+                Ok(smooth) => smooth,
+            };
+            continue;
+        } else if arg == "--unique" {
+            output.axes.unique = true;
+            continue;
+        } else if arg == "--since" {
+            let value = argv.next().ok_or(Error::MissingValue("--since"))?;
+            output.since = Some(parse_date(&value).ok_or(Error::InvalidDate(value))?);
+            continue;
+        } else if arg == "--until" {
+            let value = argv.next().ok_or(Error::MissingValue("--until"))?;
+            output.until = Some(parse_date(&value).ok_or(Error::InvalidDate(value))?);
+            continue;
+        } else if arg == "--terminal" {
+            output.terminal = true;
+            continue;
+        } else if arg == "--input" {
+            let value = argv.next().ok_or(Error::MissingValue("--input"))?;
+            input = Some(PathBuf::from(value));
+            continue;
+        } else if arg == "--host" {
+            host = Some(argv.next().ok_or(Error::MissingValue("--host"))?);
+            continue;
+        } else if arg.starts_with('-') {
+            // Logins can technically start with a hyphen, but a mistyped
+            // option is far more likely. Such a login can be given as @-login.
+            return Err(Error::UnknownOption(arg));
         }
-        let mut parts = arg.splitn(2, '/');
-        let owner = parts.next().unwrap();
-        match parts.next() {
-            Some(repo) => {
-                let owner = owner.to_owned();
-                let repo = repo.to_owned();
-                args.push(Series::Repo(owner, repo));
-            }
-            None => {
-                let owner = owner.strip_prefix('@').unwrap_or(owner).to_owned();
-                args.push(Series::Owner(owner));
+        args.push(parse_series(&arg)?);
+    }
+
+    if output.smooth > 1 && output.axes.per.is_none() {
+        return Err(Error::SmoothWithoutPer);
+    }
+
+    if let Some(since) = output.since {
+        if since >= output.until.unwrap_or_else(Utc::now) {
+            return Err(Error::EmptyRange);
+        }
+    }
+
+    output.format = match format {
+        Some(format) => format,
+        None => match output.path.as_deref().and_then(Path::extension) {
+            None => Format::Html,
+            Some(extension) => extension
+                .to_str()
+                .and_then(|extension| Format::parse(&extension.to_ascii_lowercase()))
+                .ok_or_else(|| Error::UnknownFormat(extension.to_string_lossy().into_owned()))?,
+        },
+    };
+
+    if let Some(input) = input {
+        if !args.is_empty() {
+            return Err(Error::InputWithArgs);
+        }
+        let export = json::load(&input)?;
+        let mut stars = Map::new();
+        let mut departures = Map::new();
+        for series in export.series {
+            args.push(series.name.clone());
+            if !series.departures.is_empty() {
+                departures.insert(series.name.clone(), series.departures);
             }
+            stars.insert(series.name, series.stars);
+        }
+        return render(log, &output, &args, &stars, &departures, export.fetched_at);
+    }
+
+    let mut work = Vec::new();
+    let mut stars = Map::new();
+    let mut cursors = Map::new();
+    let mut renamed = Set::new();
+    if resume {
+        if !args.is_empty() {
+            return Err(Error::ResumeWithArgs);
         }
+        let checkpoint = checkpoint::load(checkpoint_path.as_ref().unwrap())?;
+        args = checkpoint.args;
+        work = checkpoint.work;
+        stars = checkpoint.stars;
+        cursors = checkpoint.cursors;
     }
 
-    let github_token = match gh_token::get() {
+    let host = match host {
+        Some(host) => host,
+        None => match env::var_os("GH_HOST") {
+            Some(host) if !host.is_empty() => host.into_string().map_err(|_| Error::HostNonUtf8)?,
+            _ => DEFAULT_HOST.to_owned(),
+        },
+    };
+    let is_github_com = host.eq_ignore_ascii_case(DEFAULT_HOST);
+    let endpoint = if is_github_com {
+        "https://api.github.com/graphql".to_owned()
+    } else {
+        format!("https://{}/api/graphql", host)
+    };
+
+    // No token is needed if all responses are coming from a recording, so do
+    // not go looking for one, which can mean running `gh auth token`.
+    let token = if replay.is_some() {
+        Ok(String::new())
+    } else {
+        gh_token::get_for_host(&host)
+    };
+    let github_token = match token {
         Ok(token) => token,
         Err(gh_token::Error::NotConfigured(path)) => {
             let path_lossy = path.to_string_lossy();
-            let message = MISSING_TOKEN.replace("{{path}}", &path_lossy);
+            let (login, var) = if is_github_com {
+                ("gh auth login".to_owned(), "GITHUB_TOKEN")
+            } else {
+                (
+                    format!("gh auth login --hostname {}", host),
+                    "GH_ENTERPRISE_TOKEN",
+                )
+            };
+            let message = MISSING_TOKEN
+                .replace("{{path}}", &path_lossy)
+                .replace("{{login}}", &login)
+                .replace("{{var}}", var)
+                .replace("{{host}}", &host);
             eprint!("{}", message);
             process::exit(1);
         }
@@ -371,180 +839,937 @@ fn try_main(log: &mut Log) -> Result<()> {
         process::exit(1);
     }
 
-    let mut work = Vec::new();
-    let mut stars = Map::new();
-    for series in &args {
-        stars.insert(series.clone(), Set::new());
-        work.push(Work {
-            series: series.clone(),
-            cursor: Cursor(None),
-        });
+    // Requests depend on the cursors loaded from the cache, so the cache would
+    // get in the way of replaying the same requests that were recorded.
+    let use_cache = use_cache && record.is_none() && replay.is_none();
+    let cache = if use_cache { Cache::new(&host) } else { None };
+    let mut seeded = Set::new();
+    if !resume {
+        // Members of a group are fetched like any other series, but only once
+        // if they are also named elsewhere on the command line.
+        let mut members = Vec::new();
+        for series in args.iter().flat_map(Series::members) {
+            if !members.contains(series) {
+                members.push(series.clone());
+            }
+        }
+        for series in &members {
+            stars.insert(series.clone(), Set::new());
+        }
+        for series in &members {
+            let cursor = match series {
+                Series::Owner(_) => Cursor(None),
+                Series::Repo(owner, repo) => load_cached(
+                    cache.as_ref(),
+                    &refresh,
+                    &mut stars,
+                    &mut cursors,
+                    &mut seeded,
+                    owner,
+                    repo,
+                ),
+                Series::Group(..) | Series::Overlap(..) => unreachable!(),
+            };
+            work.push(Work {
+                series: series.clone(),
+                cursor,
+            });
+        }
     }
 
-    let client = Client::new();
+    let api = Api {
+        client: Client::new(),
+        endpoint,
+        authorization,
+        max_attempts,
+        record,
+        replay,
+    };
+    let mut incomplete = Vec::new();
+    let mut budget: Option<RateLimit> = None;
+    let mut last_checkpoint = Instant::now();
+    let mut size = Size::new();
+    let mut fatal = None;
     while !work.is_empty() {
-        let batch_size = cmp::min(work.len(), 50);
-        let defer = work.split_off(batch_size);
-        let batch = mem::replace(&mut work, defer);
+        // Split the pending work evenly across up to `jobs` requests, with no
+        // more than the current batch size per request.
+        let batch_size = cmp::min(work.len().div_ceil(jobs), size.batch);
+        let mut batches = Vec::new();
+        while !work.is_empty() && batches.len() < jobs {
+            let defer = work.split_off(cmp::min(work.len(), batch_size));
+            batches.push(mem::replace(&mut work, defer));
+        }
 
-        let mut request = Request {
-            query: String::new(),
-        };
-        request.query += "{\n";
-        for (i, work) in batch.iter().enumerate() {
-            let cursor = &work.cursor;
-            request.query += &match &work.series {
-                Series::Owner(owner) => query_owner(i, owner, cursor),
-                Series::Repo(owner, repo) => query_repo(i, owner, repo, cursor),
-            };
+        // Estimate the cost of this round from the cost of the previous
+        // request, and wait for the budget to reset if it would not cover it.
+        if let Some(rate_limit) = budget.take_if(|rate_limit| {
+            api.replay.is_none()
+                && rate_limit.remaining < cmp::max(rate_limit.cost, 1) * batches.len() as u64
+        }) {
+            let reset_at = rate_limit.reset_at.with_timezone(&Local);
+            let msg = format!(
+                "{} rate limit remaining, waiting until {}",
+                rate_limit.remaining,
+                reset_at.format("%H:%M:%S"),
+            );
+            log.note(&msg);
+            let wait = (rate_limit.reset_at - Utc::now())
+                .to_std()
+                .unwrap_or_default();
+            thread::sleep(wait + Duration::from_secs(1));
         }
-        request.query += "}\n";
 
-        let json = loop {
-            let response = client
-                .post("https://api.github.com/graphql")
-                .header(USER_AGENT, "dtolnay/star-history")
-                .header(AUTHORIZATION, &authorization)
-                .json(&request)
-                .send()?;
+        let retry_timeouts = !size.can_shrink();
+        let responses = thread::scope(|scope| {
+            let handles: Vec<_> = batches
+                .iter()
+                .map(|batch| {
+                    let request = build_request(batch, size.page);
+                    let api = &api;
+                    scope.spawn(move || api.fetch(&request, retry_timeouts, log))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
 
-            if response.status() == StatusCode::FORBIDDEN {
-                if let Some(retry_after) = response.headers().get(RETRY_AFTER) {
-                    let retry_after: u64 = retry_after
-                        .to_str()
-                        .map_err(Error::RetryAfterStr)?
-                        .parse()
-                        .map_err(Error::RetryAfterInt)?;
-                    let msg = format!("waiting {} seconds as requested by GitHub", retry_after);
-                    log.note(&msg);
-                    thread::sleep(Duration::from_secs(retry_after));
+        // Responses are merged in the order that their batches were taken from
+        // the work queue, regardless of which request finished first.
+        let mut latest: Option<RateLimit> = None;
+        let mut timed_out = false;
+        for (batch, response) in batches.into_iter().zip(responses) {
+            let response = match response {
+                Ok(response)
+                    if retry_timeouts || !response.errors.iter().any(Message::is_timeout) =>
+                {
+                    response
+                }
+                Ok(_) | Err(Error::Timeout) => {
+                    timed_out = true;
+                    work.extend(batch);
+                    continue;
+                }
+                Err(err) => {
+                    log.error(err);
+                    incomplete.extend(batch);
                     continue;
                 }
+            };
+            // A top-level message, such as for bad credentials, means there is
+            // no use in sending more requests. Stop after this round, but save
+            // what has been fetched so far.
+            if let Some(message) = response.message {
+                fatal.get_or_insert(Error::GitHub(message));
+                incomplete.extend(batch);
+                continue;
+            }
+            // If the whole query failed, there is no data to match up with
+            // the batch, but the series in it are not done.
+            let failed = response.data.is_empty() && !response.errors.is_empty();
+            for err in response.errors {
+                log.error(Error::GitHub(err.message));
+            }
+            if failed {
+                incomplete.extend(batch);
+                continue;
             }
 
-            break response.text()?;
-        };
-
-        let response: Response = serde_json::from_str(&json).map_err(Error::DecodeResponse)?;
-        if let Some(message) = response.message {
-            return Err(Error::GitHub(message));
-        }
-        for err in response.errors {
-            log.error(Error::GitHub(err.message));
-        }
-
-        let mut data = response.data;
-        let mut queue = batch.into_iter();
-        while let Some(node) = data.pop_front() {
-            let id = queue.next();
-            match node {
-                Data::Owner(None) | Data::Repo(None) => match id.unwrap().series {
-                    Series::Owner(owner) => return Err(Error::NoSuchUser(owner)),
-                    Series::Repo(owner, repo) => return Err(Error::NoSuchRepo(owner, repo)),
-                },
-                Data::Owner(Some(node)) => {
-                    let owner = node.login;
-                    for repo in node.repositories.nodes {
-                        data.push_back(Data::Repo(Some(repo)));
-                    }
-
-                    if node.repositories.page_info.has_next_page {
-                        work.push(Work {
-                            series: Series::Owner(owner),
-                            cursor: node.repositories.page_info.end_cursor,
+            let mut data = response.data;
+            let mut queue = batch.into_iter();
+            while let Some(node) = data.pop_front() {
+                let id = queue.next();
+                match node {
+                    Data::Owner(None) | Data::Repo(None) => {
+                        // Leave the missing series out of the graph but keep
+                        // going with the rest.
+                        // A group is drawn from whichever of its members exist,
+                        // but an overlap is meaningless without all of them.
+                        let series = id.unwrap().series;
+                        for arg in &mut args {
+                            if let Series::Group(_, members) = arg {
+                                members.retain(|member| *member != series);
+                            }
+                        }
+                        args.retain(|arg| match arg {
+                            Series::Overlap(..) => !arg.members().contains(&&series),
+                            _ => *arg != series && !arg.members().is_empty(),
+                        });
+                        log.error(match series {
+                            Series::Owner(owner) => Error::NoSuchUser(owner),
+                            Series::Repo(owner, repo) => Error::NoSuchRepo(owner, repo),
+                            Series::Group(..) | Series::Overlap(..) => unreachable!(),
                         });
                     }
-                }
-                Data::Repo(Some(node)) => {
-                    let owner = node.owner.login;
-                    let repo = node.name;
-
-                    if let Some(stargazers) = node.stargazers {
-                        let series = Series::Owner(owner.clone());
-                        let owner_stars = stars.entry(series).or_default();
-                        for star in &stargazers.edges {
-                            owner_stars.insert(star.clone());
+                    Data::Owner(Some(node)) => {
+                        let owner = node.login;
+                        for repo in node.repositories.nodes {
+                            data.push_back(Data::Repo(Some(repo)));
                         }
 
-                        let series = Series::Repo(owner.clone(), repo.clone());
-                        let repo_stars = stars.entry(series).or_default();
-                        for star in &stargazers.edges {
-                            repo_stars.insert(star.clone());
+                        if node.repositories.page_info.has_next_page {
+                            work.push(Work {
+                                series: Series::Owner(owner),
+                                cursor: node.repositories.page_info.end_cursor,
+                            });
+                        }
+                    }
+                    Data::RateLimit(rate_limit) => {
+                        if latest
+                            .as_ref()
+                            .is_none_or(|latest| rate_limit.remaining < latest.remaining)
+                        {
+                            latest = Some(rate_limit);
+                        }
+                    }
+                    Data::Repo(Some(node)) => {
+                        let owner = node.owner.login;
+                        let repo = node.name;
+                        let resolved = Series::Repo(owner.clone(), repo.clone());
+
+                        // GitHub resolves the old name of a renamed or
+                        // transferred repo to its new name. Keep filing its
+                        // stars, and asking for its later pages, under the
+                        // name that was asked for. Repos listed by an owner
+                        // were not asked for by name and keep their own.
+                        let series = match id {
+                            Some(work) => work.series,
+                            None => resolved.clone(),
+                        };
+                        if series != resolved && renamed.insert(series.clone()) {
+                            let msg = format!("{} is now {}", series, resolved);
+                            log.note(&msg);
                         }
 
-                        if stargazers.page_info.has_next_page {
+                        if let Some(stargazers) = node.stargazers {
+                            let repo_stars = stars.entry(series.clone()).or_default();
+                            repo_stars.extend(stargazers.edges);
+
+                            let end_cursor = stargazers.page_info.end_cursor;
+                            if end_cursor.0.is_some() {
+                                cursors.insert(series.clone(), end_cursor.clone());
+                            }
+
+                            if stargazers.page_info.has_next_page {
+                                work.push(Work {
+                                    series,
+                                    cursor: end_cursor,
+                                });
+                            } else if node
+                                .stargazer_count
+                                .is_some_and(|count| stars[&series].len() > count)
+                                && seeded.remove(&series)
+                            {
+                                // Someone unstarred since the cache was written.
+                                // GitHub can leave some accounts out of the
+                                // stargazers but not the count, so only a set
+                                // larger than the count is known to be stale.
+                                let msg =
+                                    format!("cached stars of {} are stale, refetching", series);
+                                log.note(&msg);
+                                stars.get_mut(&series).unwrap().clear();
+                                cursors.remove(&series);
+                                work.push(Work {
+                                    series,
+                                    cursor: Cursor(None),
+                                });
+                            } else {
+                                // The stars of a repo count toward whoever
+                                // owns it now, which is only known for sure
+                                // once it has been fetched.
+                                let repo_stars = stars[&series].clone();
+                                let owner_stars = stars.entry(Series::Owner(owner)).or_default();
+                                owner_stars.extend(repo_stars);
+                            }
+                        } else {
+                            let cursor = load_cached(
+                                cache.as_ref(),
+                                &refresh,
+                                &mut stars,
+                                &mut cursors,
+                                &mut seeded,
+                                &owner,
+                                &repo,
+                            );
                             work.push(Work {
                                 series: Series::Repo(owner, repo),
-                                cursor: stargazers.page_info.end_cursor,
+                                cursor,
                             });
                         }
-                    } else {
-                        work.push(Work {
-                            series: Series::Repo(owner, repo),
-                            cursor: Cursor(None),
-                        });
                     }
                 }
             }
+
+            log.tick();
+        }
+
+        if fatal.is_some() {
+            incomplete.append(&mut work);
+            break;
+        }
+
+        if timed_out {
+            size.shrink();
+            let msg = format!(
+                "GitHub timed out, retrying with {} per page and {} per request",
+                size.page, size.batch,
+            );
+            log.note(&msg);
+        } else {
+            size.grow();
+        }
+
+        if let Some(latest) = latest {
+            if budget.is_none() {
+                let msg = format!("{} rate limit remaining", latest.remaining);
+                log.note(&msg);
+            }
+            budget = Some(latest);
         }
 
-        log.tick();
+        if let Some(path) = &checkpoint_path {
+            if last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL && !work.is_empty() {
+                let pending = work.iter().chain(&incomplete);
+                if let Err(err) = checkpoint::save(path, &args, pending, &stars, &cursors) {
+                    log.error(err);
+                }
+                last_checkpoint = Instant::now();
+            }
+        }
     }
 
-    let now = Utc::now();
-    for set in stars.values_mut() {
-        if let Some(first) = set.iter().next() {
-            let first_time = first.time;
-            set.insert(Star {
-                time: first_time - TimeDelta::try_seconds(1).unwrap(),
-                node: Default::default(),
-            });
+    // Keep the checkpoint around only if there is something left to resume.
+    if let Some(path) = &checkpoint_path {
+        let result = if incomplete.is_empty() {
+            match fs::remove_file(path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                    Err(Error::WriteCheckpoint(path.clone(), err))
+                }
+                _ => Ok(()),
+            }
+        } else {
+            checkpoint::save(path, &args, &incomplete, &stars, &cursors)
+        };
+        if let Err(err) = result {
+            log.error(err);
         }
-        match set.iter().next_back() {
-            Some(last) if last.time >= now => {}
-            _ => {
-                set.insert(Star {
-                    time: now,
-                    node: Default::default(),
-                });
+    }
+
+    if let Some(cache) = &cache {
+        for (series, cursor) in &cursors {
+            if let Series::Repo(owner, repo) = series {
+                if let Err(err) = cache.store(owner, repo, cursor, &stars[series]) {
+                    log.error(err);
+                }
             }
         }
     }
 
-    let mut data = String::new();
-    data += "var data = [\n";
+    if let Some(err) = fatal {
+        return Err(err);
+    }
+
+    // A series is incomplete if any page of it, or for an owner any page of
+    // any of their repos, or for a group any page of any of its members, could
+    // not be fetched.
+    let mut failed = Vec::new();
+    args.retain(|arg| {
+        let is_incomplete = incomplete.iter().any(|work| {
+            arg.members()
+                .iter()
+                .any(|&member| match (member, &work.series) {
+                    (Series::Owner(owner), Series::Repo(repo_owner, _)) => {
+                        owner.eq_ignore_ascii_case(repo_owner)
+                    }
+                    (member, series) => member == series,
+                })
+        });
+        if is_incomplete {
+            failed.push(arg.to_string());
+        }
+        !is_incomplete
+    });
+    if args.is_empty() {
+        if failed.is_empty() {
+            // Every series was missing, which has already been reported.
+            process::exit(1);
+        }
+        return Err(Error::Incomplete(failed));
+    }
+
+    let mut departures = Map::new();
     for arg in &args {
-        data += "      {\"name\":\"";
-        data += &arg.to_string();
-        data += "\", \"values\":[\n";
-        let stars = &stars[arg];
-        for (i, star) in stars.iter().enumerate() {
-            data += "        {\"time\":";
-            data += &star.time.timestamp().to_string();
-            data += ", \"stars\":";
-            data += &(i.saturating_sub((star.time == now) as usize)).to_string();
-            data += "},\n";
-        }
-        data += "      ]},\n";
-    }
-    data += "    ];";
-
-    let html = include_str!("index.html").replace("var data = [];", &data);
-    let dir = env::temp_dir().join("star-history");
-    fs::create_dir_all(&dir)?;
-    let path = dir.join(format!("{}.html", now.timestamp_millis()));
-    fs::write(&path, html)?;
-
-    writeln!(log, "graph written to {}", path.display());
-    let _ = opener::open(&path);
+        let derived = match arg {
+            Series::Owner(_) | Series::Repo(..) => continue,
+            Series::Group(_, members) => members
+                .iter()
+                .flat_map(|member| &stars[member])
+                .cloned()
+                .collect(),
+            Series::Overlap(include, exclude) => {
+                let include: Vec<_> = include.iter().map(|member| &stars[member]).collect();
+                let exclude: Vec<_> = exclude.iter().map(|member| &stars[member]).collect();
+                let (arrivals, left) = overlap::stars(&include, &exclude);
+                departures.insert(arg.clone(), left);
+                arrivals
+            }
+        };
+        stars.insert(arg.clone(), derived);
+    }
+
+    render(log, &output, &args, &stars, &departures, Utc::now())?;
+    overlap::summary(log, &args, &stars, output.until);
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Incomplete(failed))
+    }
+}
+
+fn render(
+    log: &Log,
+    output: &Output,
+    args: &[Series],
+    stars: &Map<Series, Set<Star>>,
+    departures: &Map<Series, Set<Star>>,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    if output.terminal {
+        // End the line of progress output before drawing to stdout.
+        write!(log, "");
+        print!(
+            "{}",
+            terminal::render(&lines(args, stars, departures, output, now), output.axes)
+        );
+        return Ok(());
+    }
+
+    let content = match output.format {
+        Format::Html => {
+            html::render(&lines(args, stars, departures, output, now), output.axes).into_bytes()
+        }
+        Format::Svg => {
+            let (width, height) = (f64::from(output.width), f64::from(output.height));
+            let lines = lines(args, stars, departures, output, now);
+            svg::render(&lines, width, height, output.axes).into_bytes()
+        }
+        Format::Png => {
+            let lines = lines(args, stars, departures, output, now);
+            let (width, height, dpi) = (output.width, output.height, output.dpi);
+            raster::render(&lines, width, height, dpi, output.axes, log)?
+        }
+        Format::Csv => {
+            csv::render(&lines(args, stars, departures, output, now), output.axes).into_bytes()
+        }
+        Format::Json => json::render(args, stars, departures, now).into_bytes(),
+    };
+    let path = match &output.path {
+        Some(path) if path.as_os_str() == "-" => {
+            write!(log, "");
+            io::stdout().write_all(&content)?;
+            return Ok(());
+        }
+        Some(path) => {
+            fs::write(path, content).map_err(|err| Error::WriteOutput(path.clone(), err))?;
+            path.clone()
+        }
+        None => {
+            let dir = env::temp_dir().join("star-history");
+            fs::create_dir_all(&dir)?;
+            let millis = Utc::now().timestamp_millis();
+            let path = dir.join(format!("{}.{}", millis, output.format.extension()));
+            fs::write(&path, content)?;
+            path
+        }
+    };
+
+    // Data files are meant for other tools, so only graphs are opened.
+    let is_graph = match output.format {
+        Format::Csv | Format::Json => false,
+        Format::Html | Format::Svg | Format::Png => true,
+    };
+    let what = if is_graph { "graph" } else { "data" };
+    writeln!(log, "{} written to {}", what, path.display());
+    if is_graph && output.open {
+        let _ = opener::open(&path);
+    }
+
     Ok(())
 }
 
-fn query_owner(i: usize, login: &str, cursor: &Cursor) -> String {
-    r#"
-        owner$i: repositoryOwner(login: "$login") {
+fn lines(
+    args: &[Series],
+    stars: &Map<Series, Set<Star>>,
+    departures: &Map<Series, Set<Star>>,
+    output: &Output,
+    now: DateTime<Utc>,
+) -> Vec<Line> {
+    let end = output.until.unwrap_or(now);
+    args.iter()
+        .map(|arg| {
+            let name = match arg {
+                Series::Group(name, _) => name.clone(),
+                Series::Owner(_) | Series::Repo(..) | Series::Overlap(..) => arg.to_string(),
+            };
+            let unique;
+            let set = if output.axes.unique {
+                unique = first_star_per_login(&stars[arg]);
+                &unique
+            } else {
+                &stars[arg]
+            };
+            // A series with no stars by the end of the graph sits at day 0
+            // rather than at an absolute time.
+            let origin = if output.axes.age {
+                set.first()
+                    .map_or(end, |first| first.time.min(end))
+                    .timestamp()
+            } else {
+                0
+            };
+            let point = |time: DateTime<Utc>, stars, login: &str| Point {
+                time,
+                x: (time.timestamp() - origin) as f64,
+                stars,
+                login: login.to_owned(),
+            };
+
+            // Stars before the window still count toward the total, so the
+            // line starts at the number of stars as of the start of the window.
+            let before = match output.since {
+                Some(since) => set.iter().take_while(|star| star.time < since).count(),
+                None => 0,
+            };
+            let window = set.iter().skip(before).take_while(|star| star.time <= end);
+
+            // Only arrivals count as new, so departures from an overlap do
+            // not show up per period.
+            if let Some(period) = output.axes.per {
+                // Every line needs at least one point, so a series with no
+                // stars by the end of the graph gets a single empty period.
+                let start = output
+                    .since
+                    .or(set.first().map(|first| first.time))
+                    .map_or(end, |start| start.min(end));
+                let points = per_period(window, start, end, period, output.smooth)
+                    .into_iter()
+                    .map(|(time, stars)| point(time, stars, ""))
+                    .collect();
+                return Line { name, points };
+            }
+
+            // Someone leaves an overlap when they star a series that it
+            // excludes, which takes the line back down.
+            let empty = Set::new();
+            let departures = departures.get(arg).unwrap_or(&empty);
+            let gone = match output.since {
+                Some(since) => departures
+                    .iter()
+                    .take_while(|star| star.time < since)
+                    .count(),
+                None => 0,
+            };
+            let mut departures = departures
+                .iter()
+                .skip(gone)
+                .take_while(|star| star.time <= end)
+                .peekable();
+            let mut count = before - gone;
+            let mut points = Vec::new();
+            match output.since {
+                Some(since) => points.push(point(since, count as f64, "")),
+                None => {
+                    if let Some(first) = set.first().filter(|first| first.time <= end) {
+                        let time = first.time - TimeDelta::try_seconds(1).unwrap();
+                        points.push(point(time, 0.0, ""));
+                    }
+                }
+            }
+            let mut arrivals = window.peekable();
+            loop {
+                let star = match (arrivals.peek(), departures.peek()) {
+                    (Some(arrival), Some(departure)) if departure.time < arrival.time => {
+                        count -= 1;
+                        departures.next()
+                    }
+                    (Some(_), _) => {
+                        count += 1;
+                        arrivals.next()
+                    }
+                    (None, Some(_)) => {
+                        count -= 1;
+                        departures.next()
+                    }
+                    (None, None) => break,
+                };
+                let star = star.unwrap();
+                points.push(point(star.time, count as f64, &star.node.login));
+            }
+            match points.last() {
+                Some(last) if last.time >= end => {}
+                _ => points.push(point(end, count as f64, "")),
+            }
+
+            Line { name, points }
+        })
+        .collect()
+}
+
+// For an owner, someone who starred several of their repos appears once, at
+// the time of their earliest star.
+fn first_star_per_login(stars: &Set<Star>) -> Set<Star> {
+    let mut logins = Set::new();
+    stars
+        .iter()
+        .filter(|star| logins.insert(&star.node.login))
+        .cloned()
+        .collect()
+}
+
+// Number of stars in each period from the one containing `start` through the
+// one containing `end`, averaged over a trailing window of `smooth` periods.
+fn per_period<'a>(
+    stars: impl Iterator<Item = &'a Star>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    period: Period,
+    smooth: usize,
+) -> Vec<(DateTime<Utc>, f64)> {
+    let mut stars = stars.peekable();
+    let mut counts = Vec::new();
+    let mut bucket = period.floor(start);
+    while bucket <= end {
+        let next = period.next(bucket);
+        let mut count = 0;
+        while stars.next_if(|star| star.time < next).is_some() {
+            count += 1;
+        }
+        counts.push((bucket, count));
+        bucket = next;
+    }
+    (0..counts.len())
+        .map(|i| {
+            let window = &counts[(i + 1).saturating_sub(smooth)..=i];
+            let sum: usize = window.iter().map(|(_, count)| count).sum();
+            (counts[i].0, sum as f64 / window.len() as f64)
+        })
+        .collect()
+}
+
+fn parse_date(string: &str) -> Option<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(string) {
+        return Some(time.to_utc());
+    }
+    let date = NaiveDate::parse_from_str(string, "%Y-%m-%d").ok()?;
+    Some(date.and_time(NaiveTime::MIN).and_utc())
+}
+
+fn build_request(batch: &[Work], page_size: usize) -> Request {
+    let mut declarations = vec!["$first: Int!".to_owned()];
+    let mut variables = Map::new();
+    variables.insert("first".to_owned(), Value::from(page_size));
+    let mut selections = String::new();
+    for (i, work) in batch.iter().enumerate() {
+        let owner = match &work.series {
+            Series::Owner(owner) => {
+                selections += &query_owner(i);
+                owner
+            }
+            Series::Repo(owner, repo) => {
+                selections += &query_repo(i);
+                declarations.push(format!("$repo{}: String!", i));
+                variables.insert(format!("repo{}", i), Value::from(repo.as_str()));
+                owner
+            }
+            Series::Group(..) | Series::Overlap(..) => unreachable!(),
+        };
+        declarations.push(format!("$owner{}: String!", i));
+        variables.insert(format!("owner{}", i), Value::from(owner.as_str()));
+        declarations.push(format!("$cursor{}: String", i));
+        variables.insert(format!("cursor{}", i), Value::from(work.cursor.0.clone()));
+    }
+
+    let mut request = Request {
+        query: String::new(),
+        variables,
+    };
+    request.query += "query(";
+    request.query += &declarations.join(", ");
+    request.query += ") {\n";
+    request.query += &selections;
+    request.query += "
+        rateLimit {
+          cost
+          remaining
+          resetAt
+        }
+    ";
+    request.query += "}\n";
+    request
+}
+
+impl Api {
+    // Unless `retry_timeouts` is set, a timeout is reported to the caller
+    // immediately so that it can retry with a smaller request instead.
+    fn fetch(&self, request: &Request, retry_timeouts: bool, log: &Log) -> Result<Response> {
+        if let Some(dir) = &self.replay {
+            return match replay::load(dir, request)? {
+                Outcome::Response(json) => {
+                    serde_json::from_str(&json).map_err(Error::DecodeResponse)
+                }
+                Outcome::Status(status) if is_gateway_timeout(status) && !retry_timeouts => {
+                    Err(Error::Timeout)
+                }
+                Outcome::Status(status) => Err(Error::HttpStatus(status)),
+                Outcome::Timeout => Err(Error::Timeout),
+            };
+        }
+
+        let mut attempt = 1;
+        loop {
+            let response = match self
+                .client
+                .post(&self.endpoint)
+                .header(USER_AGENT, "dtolnay/star-history")
+                .header(AUTHORIZATION, &self.authorization)
+                .json(request)
+                .send()
+            {
+                Ok(response) => response,
+                Err(err) if err.is_timeout() && !retry_timeouts => {
+                    self.record(request, &Outcome::Timeout)?;
+                    return Err(Error::Timeout);
+                }
+                Err(err) if is_transient(&err) && attempt < self.max_attempts => {
+                    backoff(&mut attempt, self.max_attempts, log);
+                    continue;
+                }
+                Err(err) => return Err(Error::Reqwest(err)),
+            };
+
+            let status = response.status();
+            if is_gateway_timeout(status) && !retry_timeouts {
+                self.record(request, &Outcome::Status(status))?;
+                return Err(Error::Timeout);
+            }
+            if status == StatusCode::FORBIDDEN || status == StatusCode::TOO_MANY_REQUESTS {
+                let headers = response.headers();
+                if let Some(retry_after) = headers.get(RETRY_AFTER) {
+                    let retry_after: u64 = retry_after
+                        .to_str()
+                        .map_err(Error::RetryAfterStr)?
+                        .parse()
+                        .map_err(Error::RetryAfterInt)?;
+                    let msg = format!("waiting {} seconds as requested by GitHub", retry_after);
+                    log.note(&msg);
+                    thread::sleep(Duration::from_secs(retry_after));
+                    continue;
+                }
+                if let Some(reset_at) = rate_limit_reset(headers) {
+                    let msg = format!(
+                        "rate limit exceeded, waiting until {}",
+                        reset_at.with_timezone(&Local).format("%H:%M:%S"),
+                    );
+                    log.note(&msg);
+                    let wait = (reset_at - Utc::now()).to_std().unwrap_or_default();
+                    thread::sleep(wait + Duration::from_secs(1));
+                    continue;
+                }
+            }
+
+            if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+                if attempt < self.max_attempts {
+                    backoff(&mut attempt, self.max_attempts, log);
+                    continue;
+                }
+                self.record(request, &Outcome::Status(status))?;
+                return Err(Error::HttpStatus(status));
+            }
+
+            let json = match response.text() {
+                Ok(json) => json,
+                Err(err) if is_transient(&err) && attempt < self.max_attempts => {
+                    backoff(&mut attempt, self.max_attempts, log);
+                    continue;
+                }
+                Err(err) => return Err(Error::Reqwest(err)),
+            };
+
+            let response = serde_json::from_str(&json).map_err(Error::DecodeResponse);
+            self.record(request, &Outcome::Response(json))?;
+            return response;
+        }
+    }
+
+    fn record(&self, request: &Request, outcome: &Outcome) -> Result<()> {
+        match &self.record {
+            Some(dir) => replay::save(dir, request, outcome),
+            None => Ok(()),
+        }
+    }
+}
+
+fn is_gateway_timeout(status: StatusCode) -> bool {
+    status == StatusCode::BAD_GATEWAY || status == StatusCode::GATEWAY_TIMEOUT
+}
+
+fn is_transient(err: &reqwest::Error) -> bool {
+    err.is_connect() || err.is_timeout() || err.is_request() || err.is_body()
+}
+
+// GitHub reports exhaustion of the primary rate limit by a 403 or 429 with
+// x-ratelimit-remaining of 0, and the time at which it resets as a Unix
+// timestamp in x-ratelimit-reset.
+fn rate_limit_reset(headers: &HeaderMap) -> Option<DateTime<Utc>> {
+    let remaining = headers.get("x-ratelimit-remaining")?.to_str().ok()?;
+    if remaining.trim() != "0" {
+        return None;
+    }
+    let reset = headers.get("x-ratelimit-reset")?.to_str().ok()?;
+    DateTime::from_timestamp(reset.trim().parse().ok()?, 0)
+}
+
+// Exponential backoff starting at 1 second and capped at 1 minute, with the
+// actual delay chosen uniformly from the upper half of that range so that
+// concurrent requests do not retry in lockstep.
+fn backoff(attempt: &mut u32, max_attempts: u32, log: &Log) {
+    let delay = Duration::from_secs(cmp::min(1 << cmp::min(*attempt - 1, 6), 60));
+    let delay = delay.mul_f64(0.5 + 0.5 * random_fraction());
+    *attempt += 1;
+    let msg = format!(
+        "retrying in {:.1} seconds, attempt {} of {}",
+        delay.as_secs_f64(),
+        attempt,
+        max_attempts,
+    );
+    log.note(&msg);
+    thread::sleep(delay);
+}
+
+fn random_fraction() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+fn parse_series(arg: &str) -> Result<Series> {
+    let Some((name, members)) = arg.split_once('=') else {
+        return if arg.contains('&') {
+            parse_overlap(arg)
+        } else {
+            parse_member(arg)
+        };
+    };
+    if !is_valid_repo(name) {
+        return Err(Error::InvalidSeries(arg.to_owned()));
+    }
+    let mut group = Vec::new();
+    for member in members.split('+') {
+        let member = parse_member(member).map_err(|_| Error::InvalidSeries(arg.to_owned()))?;
+        if !group.contains(&member) {
+            group.push(member);
+        }
+    }
+    Ok(Series::Group(name.to_owned(), group))
+}
+
+fn parse_overlap(arg: &str) -> Result<Series> {
+    let mut include = Vec::new();
+    let mut exclude = Vec::new();
+    for term in arg.split('&') {
+        let (list, member) = match term.strip_prefix('!') {
+            Some(member) => (&mut exclude, member),
+            None => (&mut include, term),
+        };
+        let member = parse_member(member).map_err(|_| Error::InvalidSeries(arg.to_owned()))?;
+        if !list.contains(&member) {
+            list.push(member);
+        }
+    }
+    if include.is_empty() || include.iter().any(|member| exclude.contains(member)) {
+        return Err(Error::InvalidSeries(arg.to_owned()));
+    }
+    Ok(Series::Overlap(include, exclude))
+}
+
+fn parse_member(arg: &str) -> Result<Series> {
+    let mut parts = arg.splitn(2, '/');
+    let owner = parts.next().unwrap();
+    let series = match parts.next() {
+        Some(repo) => {
+            if !is_valid_owner(owner) || !is_valid_repo(repo) {
+                return Err(Error::InvalidSeries(arg.to_owned()));
+            }
+            let owner = owner.to_owned();
+            let repo = repo.to_owned();
+            Series::Repo(owner, repo)
+        }
+        None => {
+            let owner = owner.strip_prefix('@').unwrap_or(owner);
+            if !is_valid_owner(owner) {
+                return Err(Error::InvalidSeries(arg.to_owned()));
+            }
+            Series::Owner(owner.to_owned())
+        }
+    };
+    Ok(series)
+}
+
+// GitHub logins consist of alphanumerics and hyphens. Some accounts predate
+// the rules against leading, trailing and consecutive hyphens, so those are
+// not enforced. Underscores are allowed too because GitHub Enterprise uses them
+// in the logins of managed users.
+fn is_valid_owner(owner: &str) -> bool {
+    !owner.is_empty()
+        && owner.len() <= 39
+        && owner
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+}
+
+fn is_valid_repo(repo: &str) -> bool {
+    !repo.is_empty()
+        && repo.len() <= 100
+        && repo != "."
+        && repo != ".."
+        && repo
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' || ch == '.')
+}
+
+// Seeds the star set of a repo from the local cache, if present, and returns
+// the cursor from which to fetch any stargazers newer than the cached ones.
+fn load_cached(
+    cache: Option<&Cache>,
+    refresh: &Set<Series>,
+    stars: &mut Map<Series, Set<Star>>,
+    cursors: &mut Map<Series, Cursor>,
+    seeded: &mut Set<Series>,
+    owner: &str,
+    repo: &str,
+) -> Cursor {
+    let Some(cache) = cache else {
+        return Cursor(None);
+    };
+    let series = Series::Repo(owner.to_owned(), repo.to_owned());
+    if refresh.contains(&series) || refresh.contains(&Series::Owner(owner.to_owned())) {
+        return Cursor(None);
+    }
+    let Some(entry) = cache.load(owner, repo) else {
+        return Cursor(None);
+    };
+    stars.entry(series.clone()).or_default().extend(entry.stars);
+    cursors.insert(series.clone(), entry.cursor.clone());
+    seeded.insert(series);
+    entry.cursor
+}
+
+fn query_owner(i: usize) -> String {
+    "
+        owner$i: repositoryOwner(login: $owner$i) {
           login
-          repositories(after: $cursor, first: 10, isFork: false, privacy: PUBLIC, ownerAffiliations: [OWNER]) {
+          repositories(after: $cursor$i, first: $first, isFork: false, privacy: PUBLIC, ownerAffiliations: [OWNER]) {
             pageInfo {
               hasNextPage
               endCursor
@@ -557,20 +1782,19 @@ fn query_owner(i: usize, login: &str, cursor: &Cursor) -> String {
             }
           }
         }
-    "#
+    "
     .replace("$i", &i.to_string())
-    .replace("$login", login)
-    .replace("$cursor", &cursor.to_string())
 }
 
-fn query_repo(i: usize, owner: &str, repo: &str, cursor: &Cursor) -> String {
-    r#"
-        repo$i: repository(owner: "$owner", name: "$repo") {
+fn query_repo(i: usize) -> String {
+    "
+        repo$i: repository(owner: $owner$i, name: $repo$i) {
           name
           owner {
             login
           }
-          stargazers(after: $cursor, first: 20) {
+          stargazerCount
+          stargazers(after: $cursor$i, first: $first, orderBy: {field: STARRED_AT, direction: ASC}) {
             pageInfo {
               hasNextPage
               endCursor
@@ -583,9 +1807,235 @@ fn query_repo(i: usize, owner: &str, repo: &str, cursor: &Cursor) -> String {
             }
           }
         }
-    "#
+    "
     .replace("$i", &i.to_string())
-    .replace("$owner", owner)
-    .replace("$repo", repo)
-    .replace("$cursor", &cursor.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        lines, parse_date, parse_series, Account, Axes, Format, Map, Output, Period, Series, Star,
+    };
+    use chrono::{DateTime, Utc};
+
+    fn parse(arg: &str) -> Option<String> {
+        parse_series(arg).ok().as_ref().map(Series::to_string)
+    }
+
+    fn date(string: &str) -> DateTime<Utc> {
+        parse_date(string).unwrap()
+    }
+
+    fn output(axes: Axes, since: Option<&str>, until: &str) -> Output {
+        Output {
+            terminal: false,
+            path: None,
+            open: false,
+            format: Format::Csv,
+            axes,
+            since: since.map(date),
+            until: Some(date(until)),
+            smooth: 1,
+            width: 0,
+            height: 0,
+            dpi: 0,
+        }
+    }
+
+    // The points of the line for a single series, as (time, stars).
+    fn points(
+        stars: &[(&str, &str)],
+        departures: &[(&str, &str)],
+        output: &Output,
+    ) -> Vec<(String, f64)> {
+        let series = Series::Repo("a".to_owned(), "b".to_owned());
+        let set = |stars: &[(&str, &str)]| {
+            stars
+                .iter()
+                .map(|&(login, time)| Star {
+                    time: date(time),
+                    node: Account {
+                        login: login.to_owned(),
+                    },
+                })
+                .collect()
+        };
+        let stars = Map::from([(series.clone(), set(stars))]);
+        let departures = Map::from([(series.clone(), set(departures))]);
+        let now = date("2100-01-01");
+        let lines = lines(&[series], &stars, &departures, output, now);
+        lines[0]
+            .points
+            .iter()
+            .map(|point| (point.time.format("%F %T").to_string(), point.stars))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_member() {
+        assert_eq!(parse("dtolnay").as_deref(), Some("dtolnay"));
+        assert_eq!(parse("@dtolnay").as_deref(), Some("dtolnay"));
+        assert_eq!(parse("dtolnay/syn").as_deref(), Some("dtolnay/syn"));
+        assert_eq!(parse("-old--login-").as_deref(), Some("-old--login-"));
+        assert_eq!(parse("@-old--login-").as_deref(), Some("-old--login-"));
+        assert_eq!(parse("a/b/c"), None);
+        assert_eq!(parse("@a/b"), None);
+        assert_eq!(parse("a/.."), None);
+        assert_eq!(parse("a b"), None);
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("a/"), None);
+    }
+
+    #[test]
+    fn test_parse_group() {
+        assert_eq!(
+            parse("stack=serde-rs/serde+@dtolnay+serde-rs/serde").as_deref(),
+            Some("stack=serde-rs/serde+dtolnay"),
+        );
+        assert_eq!(parse("n="), None);
+        assert_eq!(parse("=a/b"), None);
+        assert_eq!(parse("n=a/b+"), None);
+        assert_eq!(parse("n=a=b"), None);
+        assert_eq!(parse("n/m=a"), None);
+        assert_eq!(parse("n=a/b&c/d"), None);
+    }
+
+    #[test]
+    fn test_parse_overlap() {
+        assert_eq!(parse("!a/b&c/d").as_deref(), Some("c/d&!a/b"));
+        assert_eq!(parse("a&b&!c").as_deref(), Some("a&b&!c"));
+        assert_eq!(parse("a&!a"), None);
+        assert_eq!(parse("!a&!b"), None);
+        assert_eq!(parse("a&"), None);
+        assert_eq!(parse("a&!!b"), None);
+    }
+
+    #[test]
+    fn test_departure_before_since() {
+        let stars = [("p", "2024-01-01")];
+        let departures = [("p", "2024-01-02")];
+        let output = output(Axes::default(), Some("2024-01-03"), "2024-01-04");
+        assert_eq!(
+            points(&stars, &departures, &output),
+            [
+                ("2024-01-03 00:00:00".to_owned(), 0.0),
+                ("2024-01-04 00:00:00".to_owned(), 0.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_since() {
+        let stars = [
+            ("p", "2024-01-01"),
+            ("q", "2024-01-02"),
+            ("r", "2024-01-05"),
+        ];
+        let output = output(Axes::default(), Some("2024-01-03"), "2024-01-06");
+        assert_eq!(
+            points(&stars, &[], &output),
+            [
+                ("2024-01-03 00:00:00".to_owned(), 2.0),
+                ("2024-01-05 00:00:00".to_owned(), 3.0),
+                ("2024-01-06 00:00:00".to_owned(), 3.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_until_before_first_star() {
+        let stars = [("p", "2024-01-05")];
+        let output = output(Axes::default(), None, "2024-01-03");
+        assert_eq!(
+            points(&stars, &[], &output),
+            [("2024-01-03 00:00:00".to_owned(), 0.0)],
+        );
+    }
+
+    #[test]
+    fn test_empty() {
+        let cumulative = output(Axes::default(), None, "2024-01-03T12:00:00Z");
+        assert_eq!(
+            points(&[], &[], &cumulative),
+            [("2024-01-03 12:00:00".to_owned(), 0.0)],
+        );
+
+        let axes = Axes {
+            per: Some(Period::Day),
+            ..Axes::default()
+        };
+        let per_day = output(axes, None, "2024-01-03T12:00:00Z");
+        assert_eq!(
+            points(&[], &[], &per_day),
+            [("2024-01-03 00:00:00".to_owned(), 0.0)],
+        );
+    }
+
+    #[test]
+    fn test_per_week() {
+        // 2024-01-01 is a Monday.
+        let stars = [
+            ("p", "2024-01-07T23:59:59Z"),
+            ("q", "2024-01-08"),
+            ("r", "2024-01-14T23:59:59Z"),
+        ];
+        let axes = Axes {
+            per: Some(Period::Week),
+            ..Axes::default()
+        };
+        let output = output(axes, None, "2024-01-15");
+        assert_eq!(
+            points(&stars, &[], &output),
+            [
+                ("2024-01-01 00:00:00".to_owned(), 1.0),
+                ("2024-01-08 00:00:00".to_owned(), 2.0),
+                ("2024-01-15 00:00:00".to_owned(), 0.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_per_month() {
+        let stars = [
+            ("p", "2024-01-31T23:59:59Z"),
+            ("q", "2024-02-01"),
+            ("r", "2024-02-29T23:59:59Z"),
+        ];
+        let axes = Axes {
+            per: Some(Period::Month),
+            ..Axes::default()
+        };
+        let output = output(axes, None, "2024-03-01");
+        assert_eq!(
+            points(&stars, &[], &output),
+            [
+                ("2024-01-01 00:00:00".to_owned(), 1.0),
+                ("2024-02-01 00:00:00".to_owned(), 2.0),
+                ("2024-03-01 00:00:00".to_owned(), 0.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_smooth() {
+        let stars = [
+            ("p", "2024-01-01"),
+            ("q", "2024-01-01"),
+            ("r", "2024-01-03"),
+        ];
+        let axes = Axes {
+            per: Some(Period::Day),
+            ..Axes::default()
+        };
+        let mut output = output(axes, None, "2024-01-03");
+        output.smooth = 2;
+        assert_eq!(
+            points(&stars, &[], &output),
+            [
+                ("2024-01-01 00:00:00".to_owned(), 2.0),
+                ("2024-01-02 00:00:00".to_owned(), 1.0),
+                ("2024-01-03 00:00:00".to_owned(), 0.5),
+            ],
+        );
+    }
 }
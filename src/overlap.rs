@@ -0,0 +1,179 @@
+use crate::log::Log;
+use crate::{Series, Star};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap as Map, BTreeSet as Set};
+
+// Everyone who at some point had starred every series in `include` and none
+// in `exclude`. The first set has each of them at the time of the star by which
+// they had starred all of `include`, and the second at the time of their first
+// star of anything in `exclude` after that, if any.
+pub(crate) fn stars(include: &[&Set<Star>], exclude: &[&Set<Star>]) -> (Set<Star>, Set<Star>) {
+    let mut entered: Map<&str, (&Star, usize)> = Map::new();
+    for set in include {
+        for star in first_star_per_login(set, None).into_values() {
+            entered
+                .entry(&star.node.login)
+                .and_modify(|(latest, count)| {
+                    if star.time > latest.time {
+                        *latest = star;
+                    }
+                    *count += 1;
+                })
+                .or_insert((star, 1));
+        }
+    }
+    let mut excluded: Map<&str, &Star> = Map::new();
+    for set in exclude {
+        for (login, star) in first_star_per_login(set, None) {
+            excluded
+                .entry(login)
+                .and_modify(|earliest| {
+                    if star.time < earliest.time {
+                        *earliest = star;
+                    }
+                })
+                .or_insert(star);
+        }
+    }
+
+    let mut arrivals = Set::new();
+    let mut departures = Set::new();
+    for (login, (arrival, count)) in entered {
+        if count < include.len() {
+            continue;
+        }
+        match excluded.get(login) {
+            // Already excluded by the time they would have arrived.
+            Some(departure) if departure.time <= arrival.time => {}
+            Some(departure) => {
+                arrivals.insert(arrival.clone());
+                departures.insert((*departure).clone());
+            }
+            None => {
+                arrivals.insert(arrival.clone());
+            }
+        }
+    }
+    (arrivals, departures)
+}
+
+// Prints the number of stargazers of each operand of every overlap as of
+// `until`, along with how much each pair of them have in common.
+pub(crate) fn summary(
+    log: &Log,
+    args: &[Series],
+    stars: &Map<Series, Set<Star>>,
+    until: Option<DateTime<Utc>>,
+) {
+    for arg in args {
+        let Series::Overlap(include, _) = arg else {
+            continue;
+        };
+        writeln!(log, "overlap of {}:", arg);
+        let members = arg.members();
+        let logins: Vec<Set<&str>> = members
+            .iter()
+            .map(|member| {
+                first_star_per_login(&stars[*member], until)
+                    .into_keys()
+                    .collect()
+            })
+            .collect();
+        for (member, logins) in members.iter().zip(&logins) {
+            writeln!(log, "  {}: {} stargazers", member, logins.len());
+        }
+        for i in 0..members.len() {
+            for j in i + 1..members.len() {
+                let common = logins[i].intersection(&logins[j]).count();
+                let either = logins[i].union(&logins[j]).count();
+                let jaccard = if either == 0 {
+                    0.0
+                } else {
+                    common as f64 / either as f64
+                };
+                writeln!(
+                    log,
+                    "  {} and {}: {} in common, Jaccard similarity {:.3}",
+                    members[i], members[j], common, jaccard,
+                );
+            }
+        }
+        // Operands are listed with those in `include` first.
+        let (included, excluded) = logins.split_at(include.len());
+        let result = included[0]
+            .iter()
+            .filter(|login| included[1..].iter().all(|set| set.contains(*login)))
+            .filter(|login| !excluded.iter().any(|set| set.contains(*login)))
+            .count();
+        writeln!(log, "  result: {} stargazers", result);
+    }
+}
+
+fn first_star_per_login(stars: &Set<Star>, until: Option<DateTime<Utc>>) -> Map<&str, &Star> {
+    let mut first = Map::new();
+    for star in stars {
+        if until.is_some_and(|until| star.time > until) {
+            break;
+        }
+        first.entry(star.node.login.as_str()).or_insert(star);
+    }
+    first
+}
+
+#[cfg(test)]
+mod tests {
+    use super::stars;
+    use crate::{Account, Star};
+    use chrono::DateTime;
+    use std::collections::BTreeSet as Set;
+
+    fn set(stars: &[(&str, i64)]) -> Set<Star> {
+        stars
+            .iter()
+            .map(|&(login, time)| Star {
+                time: DateTime::from_timestamp(time, 0).unwrap(),
+                node: Account {
+                    login: login.to_owned(),
+                },
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_intersection() {
+        let a = set(&[("p", 100), ("q", 200), ("r", 300)]);
+        let b = set(&[("q", 150), ("p", 250), ("s", 350)]);
+        let (arrivals, departures) = stars(&[&a, &b], &[]);
+        assert_eq!(arrivals, set(&[("q", 200), ("p", 250)]));
+        assert!(departures.is_empty());
+    }
+
+    #[test]
+    fn test_difference() {
+        let a = set(&[("p", 100), ("q", 200)]);
+        let b = set(&[("p", 300)]);
+        let (arrivals, departures) = stars(&[&a], &[&b]);
+        assert_eq!(arrivals, set(&[("p", 100), ("q", 200)]));
+        assert_eq!(departures, set(&[("p", 300)]));
+    }
+
+    #[test]
+    fn test_excluded_first() {
+        let a = set(&[("p", 200), ("q", 200)]);
+        let b = set(&[("p", 100)]);
+        let c = set(&[("q", 200), ("q", 250)]);
+        let (arrivals, departures) = stars(&[&a], &[&b, &c]);
+        assert!(arrivals.is_empty());
+        assert!(departures.is_empty());
+    }
+
+    #[test]
+    fn test_first_star_per_login() {
+        // An owner's stars have someone once per repo they starred.
+        let owner = set(&[("p", 100), ("p", 400), ("q", 200)]);
+        let b = set(&[("p", 300), ("q", 500), ("q", 600)]);
+        let (arrivals, departures) = stars(&[&owner], &[&b]);
+        assert_eq!(arrivals, set(&[("p", 100), ("q", 200)]));
+        assert_eq!(departures, set(&[("p", 300), ("q", 500)]));
+    }
+}
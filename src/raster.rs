@@ -0,0 +1,109 @@
+use crate::chart::{Axes, Line};
+use crate::log::Log;
+use crate::{svg, Error, Result};
+use png::{BitDepth, ColorType, Encoder, PixelDimensions, Unit};
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg::fontdb::{Database, Family, Query};
+use resvg::usvg::{self, Tree};
+use std::sync::Arc;
+
+// CSS pixels are defined as 1/96 of an inch.
+const CSS_DPI: f64 = 96.0;
+
+// Rendering allocates 4 bytes per pixel up front, twice over for encoding, so
+// refuse sizes that would take more than about half a gigabyte.
+const MAX_SIDE: u32 = 16384;
+const MAX_PIXELS: u32 = 1 << 26;
+
+pub(crate) fn render(
+    lines: &[Line],
+    width: u32,
+    height: u32,
+    dpi: u32,
+    axes: Axes,
+    log: &Log,
+) -> Result<Vec<u8>> {
+    let svg = svg::render(lines, f64::from(width), f64::from(height), axes);
+
+    let mut fontdb = Database::new();
+    fontdb.load_system_fonts();
+    select_sans_serif(&mut fontdb, log);
+    let options = usvg::Options {
+        fontdb: Arc::new(fontdb),
+        ..usvg::Options::default()
+    };
+    let tree = Tree::from_str(&svg, &options).expect("generated svg is valid");
+
+    let scale = f64::from(dpi) / CSS_DPI;
+    // A tiny size at a low resolution still gets at least one pixel.
+    let pixel_width = (f64::from(width) * scale).round().max(1.0) as u32;
+    let pixel_height = (f64::from(height) * scale).round().max(1.0) as u32;
+    let too_large = Error::ImageTooLarge(pixel_width, pixel_height);
+    if pixel_width > MAX_SIDE
+        || pixel_height > MAX_SIDE
+        || pixel_width
+            .checked_mul(pixel_height)
+            .is_none_or(|pixels| pixels > MAX_PIXELS)
+    {
+        return Err(too_large);
+    }
+    let mut pixmap = Pixmap::new(pixel_width, pixel_height).ok_or(too_large)?;
+    let transform = Transform::from_scale(scale as f32, scale as f32);
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    let mut data = Vec::with_capacity(pixmap.data().len());
+    for pixel in pixmap.pixels() {
+        let color = pixel.demultiply();
+        data.extend_from_slice(&[color.red(), color.green(), color.blue(), color.alpha()]);
+    }
+
+    let mut png = Vec::new();
+    let mut encoder = Encoder::new(&mut png, pixel_width, pixel_height);
+    encoder.set_color(ColorType::Rgba);
+    encoder.set_depth(BitDepth::Eight);
+    // Record the resolution so that documents and slide decks that honor it
+    // place the image at its intended physical size.
+    let pixels_per_meter = (f64::from(dpi) / 0.0254).round() as u32;
+    encoder.set_pixel_dims(Some(PixelDimensions {
+        xppu: pixels_per_meter,
+        yppu: pixels_per_meter,
+        unit: Unit::Meter,
+    }));
+    let mut writer = encoder.write_header().map_err(Error::EncodePng)?;
+    writer.write_image_data(&data).map_err(Error::EncodePng)?;
+    writer.finish().map_err(Error::EncodePng)?;
+    Ok(png)
+}
+
+// The sans-serif family configured by fontconfig is often not one that is
+// actually installed, in which case usvg draws no text at all. Fall back to
+// whichever installed family looks most like a sans-serif font.
+fn select_sans_serif(fontdb: &mut Database, log: &Log) {
+    let query = Query {
+        families: &[Family::SansSerif],
+        ..Query::default()
+    };
+    if fontdb.query(&query).is_some() {
+        return;
+    }
+    let mut fallback = None;
+    for face in fontdb.faces() {
+        let Some((family, _language)) = face.families.first() else {
+            continue;
+        };
+        let is_sans = family.contains("Sans") && !face.monospaced;
+        if is_sans || fallback.is_none() {
+            fallback = Some(family.clone());
+        }
+        if is_sans {
+            break;
+        }
+    }
+    match fallback {
+        Some(family) => fontdb.set_sans_serif_family(family),
+        None => writeln!(
+            log,
+            "warning: no fonts found, png will be rendered without text"
+        ),
+    }
+}
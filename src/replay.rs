@@ -0,0 +1,88 @@
+use crate::{Error, Request, Result};
+use reqwest::StatusCode;
+use serde_derive::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+
+// Failures are recorded too, so that replaying goes through the same sequence
+// of retries and smaller requests as the original run.
+pub(crate) enum Outcome {
+    Response(String),
+    // A response with this status and no body worth keeping.
+    Status(StatusCode),
+    // No response at all within the client's timeout.
+    Timeout,
+}
+
+#[derive(Serialize)]
+struct RecordRef<'a> {
+    request: &'a Request,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    #[serde(skip_serializing_if = "Not::not")]
+    timeout: bool,
+}
+
+#[derive(Deserialize)]
+struct Record {
+    response: Option<String>,
+    status: Option<u16>,
+    #[serde(default)]
+    timeout: bool,
+}
+
+pub(crate) fn load(dir: &Path, request: &Request) -> Result<Outcome> {
+    let path = path(dir, request);
+    let content = fs::read(&path).map_err(|err| Error::Replay(path.clone(), err))?;
+    let record: Record = serde_json::from_slice(&content).map_err(Error::DecodeResponse)?;
+    if let Some(response) = record.response {
+        Ok(Outcome::Response(response))
+    } else if let Some(status) = record.status {
+        StatusCode::from_u16(status)
+            .map(Outcome::Status)
+            .map_err(|err| Error::Replay(path, io::Error::new(io::ErrorKind::InvalidData, err)))
+    } else if record.timeout {
+        Ok(Outcome::Timeout)
+    } else {
+        let err = io::Error::new(io::ErrorKind::InvalidData, "recording has no outcome");
+        Err(Error::Replay(path, err))
+    }
+}
+
+pub(crate) fn save(dir: &Path, request: &Request, outcome: &Outcome) -> Result<()> {
+    let path = path(dir, request);
+    let record = RecordRef {
+        request,
+        response: match outcome {
+            Outcome::Response(response) => Some(response),
+            Outcome::Status(_) | Outcome::Timeout => None,
+        },
+        status: match outcome {
+            Outcome::Status(status) => Some(status.as_u16()),
+            Outcome::Response(_) | Outcome::Timeout => None,
+        },
+        timeout: matches!(outcome, Outcome::Timeout),
+    };
+    let json = serde_json::to_vec_pretty(&record).unwrap();
+    fs::create_dir_all(dir)
+        .and_then(|()| fs::write(&path, json))
+        .map_err(|err| Error::Record(path, err))
+}
+
+// Recordings are keyed by a hash of the request, which is deterministic for a
+// given set of arguments because the request's variables are held in a sorted
+// map. FNV-1a is used rather than std's DefaultHasher because its output must
+// be stable across Rust versions.
+fn path(dir: &Path, request: &Request) -> PathBuf {
+    let json = serde_json::to_vec(request).unwrap();
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in json {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    dir.join(format!("{:016x}.json", hash))
+}
@@ -0,0 +1,37 @@
+use std::cmp;
+
+// Number of items requested per page of a connection, and number of series
+// queried per request. Large pages need fewer round trips but are more likely
+// to make GitHub time out, so these shrink on timeouts and grow back on
+// success.
+pub(crate) struct Size {
+    pub page: usize,
+    pub batch: usize,
+}
+
+const MAX: Size = Size {
+    page: 100,
+    batch: 50,
+};
+
+const MIN: Size = Size { page: 10, batch: 1 };
+
+impl Size {
+    pub fn new() -> Self {
+        MAX
+    }
+
+    pub fn can_shrink(&self) -> bool {
+        self.page > MIN.page || self.batch > MIN.batch
+    }
+
+    pub fn shrink(&mut self) {
+        self.page = cmp::max(self.page / 2, MIN.page);
+        self.batch = cmp::max(self.batch / 2, MIN.batch);
+    }
+
+    pub fn grow(&mut self) {
+        self.page = cmp::min(self.page + self.page / 4 + 1, MAX.page);
+        self.batch = cmp::min(self.batch + self.batch / 4 + 1, MAX.batch);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::chart::{self, Axes, Line, Scale, PALETTE};
+use std::fmt::Write;
+
+// Same layout as index.html.
+const MARGIN_TOP: f64 = 20.0;
+const MARGIN_RIGHT: f64 = 100.0;
+const MARGIN_BOTTOM: f64 = 30.0;
+const MARGIN_LEFT: f64 = 50.0;
+
+pub(crate) fn render(lines: &[Line], total_width: f64, total_height: f64, axes: Axes) -> String {
+    let width = (total_width - MARGIN_LEFT - MARGIN_RIGHT).max(0.0);
+    let height = (total_height - MARGIN_TOP - MARGIN_BOTTOM).max(0.0);
+    let scale = Scale::new(lines, width, height, axes);
+    let colors = chart::colors(lines);
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="14">"#,
+        w = total_width,
+        h = total_height,
+    );
+    let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#);
+    let _ = writeln!(
+        svg,
+        r#"<g transform="translate({} {})">"#,
+        MARGIN_LEFT, MARGIN_TOP,
+    );
+
+    for (i, line) in lines.iter().enumerate() {
+        let y = i as f64 * 20.0;
+        let _ = writeln!(
+            svg,
+            r#"<rect x="50" y="{}" width="10" height="10" fill="{}"/>"#,
+            y, PALETTE[colors[i]],
+        );
+        let _ = writeln!(
+            svg,
+            r#"<text x="64" y="{}">{}</text>"#,
+            y + 9.0,
+            escape(&line.name),
+        );
+    }
+
+    let _ = writeln!(
+        svg,
+        r#"<g transform="translate(0 {})" fill="none" font-size="10" text-anchor="middle">"#,
+        height,
+    );
+    let _ = writeln!(
+        svg,
+        r#"<path stroke="black" shape-rendering="crispEdges" d="M0.5,6V0.5H{}V6"/>"#,
+        width + 0.5,
+    );
+    for (x, label) in scale.x_ticks(10) {
+        let _ = writeln!(
+            svg,
+            r#"<g transform="translate({} 0)"><line stroke="black" shape-rendering="crispEdges" y2="6"/><text fill="black" y="9" dy="0.71em">{}</text></g>"#,
+            coordinate(scale.x(x) + 0.5),
+            escape(&label),
+        );
+    }
+    let _ = writeln!(svg, "</g>");
+
+    let _ = writeln!(svg, r#"<g fill="none" font-size="10" text-anchor="end">"#);
+    let _ = writeln!(
+        svg,
+        r#"<path stroke="black" shape-rendering="crispEdges" d="M-6,{}H0.5V0.5H-6"/>"#,
+        height + 0.5,
+    );
+    for (stars, label) in scale.star_ticks(10) {
+        let _ = writeln!(
+            svg,
+            r#"<g transform="translate(0 {})"><line stroke="black" shape-rendering="crispEdges" x2="-6"/><text fill="black" x="-9" dy="0.32em">{}</text></g>"#,
+            coordinate(scale.y(stars) + 0.5),
+            escape(&label),
+        );
+    }
+    let _ = writeln!(svg, "</g>");
+
+    let _ = writeln!(
+        svg,
+        r#"<text transform="rotate(-90)" y="6" dy=".71em" text-anchor="end">{}</text>"#,
+        axes.star_label(),
+    );
+    if axes.age {
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}" text-anchor="end">Days since first star</text>"#,
+            width,
+            height - 6.0,
+        );
+    }
+
+    for (i, line) in lines.iter().enumerate() {
+        let mut path = String::new();
+        for point in &line.points {
+            path.push(if path.is_empty() { 'M' } else { 'L' });
+            path += &coordinate(scale.x(point.x));
+            path.push(',');
+            path += &coordinate(scale.y(point.stars));
+        }
+        let _ = writeln!(
+            svg,
+            r#"<path fill="none" stroke="{}" stroke-width="1.5" d="{}"/>"#,
+            PALETTE[colors[i]], path,
+        );
+        if let Some(last) = line.points.last() {
+            let _ = writeln!(
+                svg,
+                r#"<text transform="translate({} {})" x="3" dy=".35em">{}</text>"#,
+                coordinate(scale.x(last.x)),
+                coordinate(scale.y(last.stars)),
+                escape(&line.name),
+            );
+        }
+    }
+
+    let _ = writeln!(svg, "</g>");
+    let _ = writeln!(svg, "</svg>");
+    svg
+}
+
+fn coordinate(value: f64) -> String {
+    let mut string = format!("{:.2}", value);
+    while string.ends_with('0') {
+        string.pop();
+    }
+    if string.ends_with('.') {
+        string.pop();
+    }
+    if string == "-0" {
+        string.remove(0);
+    }
+    string
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped += "&amp;",
+            '<' => escaped += "&lt;",
+            '>' => escaped += "&gt;",
+            '"' => escaped += "&quot;",
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
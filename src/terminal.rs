@@ -0,0 +1,214 @@
+use crate::chart::{self, Axes, Line, Scale};
+use std::env;
+use std::fmt::Write;
+use std::io::{self, IsTerminal};
+use terminal_size::{terminal_size, Height, Width};
+
+// Closest basic ANSI colors to the palette used by the other renderers.
+const ANSI: [u8; 10] = [34, 33, 32, 31, 35, 91, 95, 90, 93, 36];
+
+// Braille characters are 2 dots wide and 4 dots tall.
+const DOT_WIDTH: usize = 2;
+const DOT_HEIGHT: usize = 4;
+
+const DEFAULT_COLUMNS: usize = 80;
+const MIN_COLUMNS: usize = 40;
+const MAX_ROWS: usize = 20;
+const MIN_ROWS: usize = 5;
+
+pub(crate) fn render(lines: &[Line], axes: Axes) -> String {
+    let (columns, rows) = match terminal_size() {
+        Some((Width(columns), Height(rows))) => (usize::from(columns), Some(usize::from(rows))),
+        None => {
+            let columns = env::var("COLUMNS")
+                .ok()
+                .and_then(|columns| columns.parse().ok());
+            (columns.unwrap_or(DEFAULT_COLUMNS), None)
+        }
+    };
+    let columns = columns.max(MIN_COLUMNS);
+    let color = io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none();
+    let colors = chart::colors(lines);
+
+    let mut legend = vec![String::new()];
+    let mut legend_width = 0;
+    for (i, line) in lines.iter().enumerate() {
+        let width = line.name.chars().count() + 2;
+        if legend_width > 0 && legend_width + 2 + width > columns {
+            legend.push(String::new());
+            legend_width = 0;
+        }
+        let entry = legend.last_mut().unwrap();
+        if legend_width > 0 {
+            *entry += "  ";
+            legend_width += 2;
+        }
+        *entry += &paint(&format!("■ {}", line.name), colors[i], color);
+        legend_width += width;
+    }
+
+    // Leave room for the legend, the time axis and the shell prompt.
+    let rows = match rows {
+        Some(rows) => rows.saturating_sub(legend.len() + 4),
+        None => MAX_ROWS,
+    };
+    let rows = rows.clamp(MIN_ROWS, MAX_ROWS);
+
+    // The plot gets whatever width is not taken up by the star labels.
+    let mut scale = Scale::new(lines, 0.0, (rows * DOT_HEIGHT - 1) as f64, axes);
+    let star_ticks = scale.star_ticks(rows / 4);
+    let star_ticks: Vec<_> = star_ticks
+        .into_iter()
+        .filter(|(_, label)| !label.is_empty())
+        .collect();
+    let label_width = star_ticks
+        .iter()
+        .map(|(_, label)| label.chars().count())
+        .max()
+        .unwrap_or(1);
+    let cells = columns.saturating_sub(label_width + 2).max(1);
+    scale.width = (cells * DOT_WIDTH - 1) as f64;
+
+    let mut canvas = Canvas::new(cells, rows);
+    for (i, line) in lines.iter().enumerate() {
+        let mut previous = None;
+        for point in &line.points {
+            let x = scale.x(point.x).round() as isize;
+            let y = scale.y(point.stars).round() as isize;
+            match previous {
+                Some((x0, y0)) => canvas.line(x0, y0, x, y, colors[i]),
+                None => canvas.dot(x, y, colors[i]),
+            }
+            previous = Some((x, y));
+        }
+    }
+
+    let mut star_labels = vec![None; rows];
+    for (stars, label) in star_ticks {
+        let row = (scale.y(stars).round() as usize / DOT_HEIGHT).min(rows - 1);
+        star_labels[row] = Some(label);
+    }
+
+    let mut out = String::new();
+    for entry in legend {
+        out += &entry;
+        out.push('\n');
+    }
+    for (row, label) in star_labels.iter().enumerate() {
+        let _ = match label {
+            Some(label) => write!(out, "{:>1$} ┤", label, label_width),
+            None => write!(out, "{:1$} │", "", label_width),
+        };
+        for cell in &canvas.cells[row * cells..(row + 1) * cells] {
+            if cell.dots == 0 {
+                out.push(' ');
+            } else {
+                let ch = char::from_u32(0x2800 + u32::from(cell.dots)).unwrap();
+                out += &paint(&ch.to_string(), cell.color, color);
+            }
+        }
+        out.truncate(out.trim_end_matches(' ').len());
+        out.push('\n');
+    }
+
+    let mut axis: Vec<char> = vec!['─'; cells];
+    let mut time_labels: Vec<char> = vec![' '; cells];
+    let mut next_free = 0;
+    for (x, label) in scale.x_ticks(cells / 12) {
+        let column = (scale.x(x).round() as usize / DOT_WIDTH).min(cells - 1);
+        axis[column] = '┬';
+        let label: Vec<char> = label.chars().collect();
+        let start = column.saturating_sub(label.len() / 2);
+        if start < next_free || start + label.len() > cells {
+            continue;
+        }
+        time_labels[start..start + label.len()].copy_from_slice(&label);
+        next_free = start + label.len() + 1;
+    }
+    let _ = write!(out, "{:1$} └", "", label_width);
+    out.extend(axis);
+    out.push('\n');
+    let _ = write!(out, "{:1$}  ", "", label_width);
+    out.extend(time_labels);
+    out.truncate(out.trim_end().len());
+    out.push('\n');
+    if axes.age {
+        let title = "days since first star";
+        let _ = writeln!(out, "{:>1$}", title, label_width + 2 + cells);
+    }
+    out
+}
+
+fn paint(text: &str, color: usize, enabled: bool) -> String {
+    if enabled {
+        format!("\x1b[{}m{}\x1b[0m", ANSI[color], text)
+    } else {
+        text.to_owned()
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Cell {
+    dots: u8,
+    color: usize,
+}
+
+struct Canvas {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Canvas {
+            width,
+            height,
+            cells: vec![Cell { dots: 0, color: 0 }; width * height],
+        }
+    }
+
+    fn dot(&mut self, x: isize, y: isize, color: usize) {
+        let (Ok(x), Ok(y)) = (usize::try_from(x), usize::try_from(y)) else {
+            return;
+        };
+        let (column, row) = (x / DOT_WIDTH, y / DOT_HEIGHT);
+        if column >= self.width || row >= self.height {
+            return;
+        }
+        let bit = match (x % DOT_WIDTH, y % DOT_HEIGHT) {
+            (0, 3) => 0x40,
+            (0, dy) => 1 << dy,
+            (_, 3) => 0x80,
+            (_, dy) => 0x08 << dy,
+        };
+        let cell = &mut self.cells[row * self.width + column];
+        cell.dots |= bit;
+        cell.color = color;
+    }
+
+    // Bresenham's line algorithm.
+    fn line(&mut self, x0: isize, y0: isize, x1: isize, y1: isize, color: usize) {
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let (mut x, mut y) = (x0, y0);
+        let mut error = dx + dy;
+        loop {
+            self.dot(x, y, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * error;
+            if e2 >= dy {
+                error += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                error += dx;
+                y += sy;
+            }
+        }
+    }
+}